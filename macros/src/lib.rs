@@ -0,0 +1,208 @@
+//! Procedural macros for [hauchiwa](https://docs.rs/hauchiwa).
+//!
+//! This crate is re-exported by `hauchiwa` behind the `macros` feature, it
+//! should not be used directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, LitStr, parse_macro_input};
+
+/// Implements `hauchiwa::Dependencies` for a struct whose fields are handles.
+///
+/// Every field has to implement `Dependencies` itself, which covers `One<T>`,
+/// `Many<T>`, `Vec<H>`, tuples of handles and other derived structs. The
+/// resolved output is a generated `{Name}View<'a>` struct with the same field
+/// names and visibility, holding the resolved value of each field.
+#[proc_macro_derive(Dependencies)]
+pub fn derive_dependencies(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "#[derive(Dependencies)] does not support generic structs",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "#[derive(Dependencies)] requires a struct with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "#[derive(Dependencies)] can only be used on structs",
+            ));
+        }
+    };
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let view = format_ident!("{}View", name);
+
+    let names: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let field_vis: Vec<_> = fields.iter().map(|field| &field.vis).collect();
+
+    let krate = quote!(::hauchiwa::__private);
+    let doc = format!("Resolved dependencies of [`{name}`].");
+
+    // Each field may resolve to several outputs (e.g. `Vec<H>`), so the
+    // outputs and tracking slots are split by the number of dependencies
+    // reported by the field itself.
+    Ok(quote! {
+        #[doc = #doc]
+        #vis struct #view<'a> {
+            #(
+                #field_vis #names: <#types as #krate::Dependencies>::Output<'a>,
+            )*
+        }
+
+        impl #krate::Dependencies for #name {
+            type Output<'a> = #view<'a>;
+
+            fn dependencies(&self) -> ::std::vec::Vec<#krate::NodeIndex> {
+                let mut dependencies = ::std::vec::Vec::new();
+                #(
+                    dependencies.extend(#krate::Dependencies::dependencies(&self.#names));
+                )*
+                dependencies
+            }
+
+            #[allow(unused_assignments)]
+            fn resolve<'a>(
+                &self,
+                outputs: &'a [#krate::Dynamic],
+            ) -> (#krate::Tracking, Self::Output<'a>) {
+                let mut tracking = #krate::Tracking::default();
+                let mut offset = 0;
+
+                let view = #view {
+                    #(
+                        #names: {
+                            let len = #krate::Dependencies::dependencies(&self.#names).len();
+                            let (edges, output) = #krate::Dependencies::resolve(
+                                &self.#names,
+                                &outputs[offset..offset + len],
+                            );
+                            tracking.edges.extend(edges.edges);
+                            offset += len;
+                            output
+                        },
+                    )*
+                };
+
+                (tracking, view)
+            }
+
+            #[allow(unused_assignments)]
+            fn is_valid(
+                &self,
+                old_tracking: &[::std::option::Option<#krate::TrackerState>],
+                new_outputs: &[#krate::Dynamic],
+                updated_nodes: &::std::collections::HashSet<#krate::NodeIndex>,
+            ) -> bool {
+                let mut offset = 0;
+
+                #(
+                    let len = #krate::Dependencies::dependencies(&self.#names).len();
+                    if !#krate::Dependencies::is_valid(
+                        &self.#names,
+                        &old_tracking[offset..offset + len],
+                        &new_outputs[offset..offset + len],
+                        updated_nodes,
+                    ) {
+                        return false;
+                    }
+                    offset += len;
+                )*
+
+                true
+            }
+        }
+    })
+}
+
+/// Embeds a directory in the executable as a `hauchiwa::vfs::EmbeddedFs`.
+///
+/// The path is relative to the directory of the crate's `Cargo.toml`. Files
+/// keep the path they have there, e.g. `content/index.md` for
+/// `embed_dir!("content")`, so the same globs work with the embedded files
+/// and with the disk.
+#[proc_macro]
+pub fn embed_dir(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as LitStr);
+    expand_embed(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_embed(input: &LitStr) -> Result<proc_macro2::TokenStream, Error> {
+    let error = |message: String| Error::new(input.span(), message);
+
+    let root = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| error("CARGO_MANIFEST_DIR is not set".into()))?;
+    let dir = input.value();
+    let prefix = dir.trim_start_matches("./").trim_end_matches('/');
+
+    let mut files = Vec::new();
+    walk(
+        &std::path::Path::new(&root).join(prefix),
+        prefix,
+        &mut files,
+    )
+    .map_err(|e| error(format!("failed to embed `{dir}`: {e}")))?;
+    files.sort();
+
+    let entries = files
+        .iter()
+        .map(|(path, full)| quote!((#path, include_bytes!(#full) as &[u8])));
+
+    Ok(quote! {
+        ::hauchiwa::vfs::EmbeddedFs::new(&[#(#entries),*])
+    })
+}
+
+/// Collects `(path, absolute path)` pairs of every file under `dir`.
+fn walk(
+    dir: &std::path::Path,
+    path: &str,
+    files: &mut Vec<(String, String)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let full = entry.path();
+        let (Some(name), Some(full_str)) =
+            (entry.file_name().to_str().map(str::to_owned), full.to_str())
+        else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "non-UTF-8 path",
+            ));
+        };
+
+        let path = match path.is_empty() {
+            true => name,
+            false => format!("{path}/{name}"),
+        };
+
+        if full.is_dir() {
+            walk(&full, &path, files)?;
+        } else {
+            files.push((path, full_str.to_owned()));
+        }
+    }
+    Ok(())
+}
@@ -1,21 +1,35 @@
 use std::any::type_name;
 use std::borrow::Cow;
+use std::collections::HashMap;
+#[cfg(feature = "live")]
+use std::collections::HashSet;
 use std::marker::PhantomData;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use camino::Utf8PathBuf;
 use glob::Pattern;
 use petgraph::Graph;
+use petgraph::graph::NodeIndex;
 
 use crate::core::{Environment, Mode, Store};
 use crate::engine::{
-    Dependencies, Many, NodeGather, NodeMap, NodeScatter, One, Task, TypedCoarse, TypedFine,
-    run_once_parallel,
+    Codec, Cutoff, Dependencies, Limit, Many, NodeFilter, NodeGather, NodeGroup, NodeJoin, NodeMap,
+    NodePaginate, NodeScatter, NodeScatterKeyed, NodeSort, One, OutputHasher, Persistent,
+    ResourceClass, Task, Timings, TypedCoarse, TypedFine, find_required, run_once_parallel,
+    run_subgraph_parallel,
 };
 use crate::error::HauchiwaError;
+use crate::hooks::Hooks;
 use crate::loader::Input;
+use crate::output::OutputFilter;
+#[cfg(feature = "live")]
+use crate::server::ServerConfig;
+use crate::snapshot::{BuiltSite, CommitReport, Snapshot, SnapshotView};
+use crate::stat::StatCache;
 use crate::utils::ProgressStyles;
-use crate::{Diagnostics, TaskContext};
+use crate::vfs::{Disk, Vfs};
+use crate::{Diagnostics, Page, TaskContext};
 
 /// The blueprint for your static site.
 ///
@@ -39,6 +53,19 @@ pub struct Blueprint<G: Send + Sync = ()> {
     pub(crate) out_dir: Utf8PathBuf,
     pub(crate) cache_dir: Utf8PathBuf,
     pub(crate) progress: ProgressStyles,
+    pub(crate) continue_on_error: bool,
+    pub(crate) persisted: HashMap<NodeIndex, Arc<dyn Codec>>,
+    pub(crate) cutoff: HashMap<NodeIndex, OutputHasher>,
+    pub(crate) limits: HashMap<NodeIndex, Limit>,
+    pub(crate) timeouts: HashMap<NodeIndex, Duration>,
+    pub(crate) hooks: Hooks<G>,
+    pub(crate) vfs: Arc<dyn Vfs>,
+    #[cfg(feature = "live")]
+    pub(crate) server: ServerConfig,
+    /// Tasks which only produce stylesheets, swapped in place by the
+    /// live-reload script when nothing else changed.
+    #[cfg(feature = "live")]
+    pub(crate) stylesheets: HashSet<NodeIndex>,
 }
 
 impl<G: Send + Sync + 'static> Blueprint<G> {
@@ -61,6 +88,16 @@ impl<G: Send + Sync + 'static> Blueprint<G> {
         self
     }
 
+    /// Sets the filesystem that loaders and static files are read from
+    /// (default: [`Disk`]).
+    ///
+    /// See the [`vfs`](crate::vfs) module for the available implementations.
+    #[must_use]
+    pub fn set_vfs(mut self, vfs: impl Vfs + 'static) -> Self {
+        self.vfs = Arc::new(vfs);
+        self
+    }
+
     /// Overrides the progress bar styles used during builds.
     ///
     /// See [`ProgressStyles`] for the available fields and their defaults.
@@ -70,16 +107,138 @@ impl<G: Send + Sync + 'static> Blueprint<G> {
         self
     }
 
+    /// Configures the development server started by [`Website::watch`].
+    ///
+    /// See [`ServerConfig`] for the available fields and their defaults.
+    #[cfg(feature = "live")]
+    #[must_use]
+    pub fn set_server(mut self, config: ServerConfig) -> Self {
+        self.server = config;
+        self
+    }
+
+    /// Keeps building after a task fails (default: `false`).
+    ///
+    /// Tasks that depend on a failed task are skipped, while independent tasks
+    /// still run. Loaders drop individual files that fail to load and keep the
+    /// rest of their output. Every failure is collected in
+    /// [`Diagnostics::failures`], and [`Website::build`] reports all of them at
+    /// the end. In watch mode the pages that still built are written to `dist`.
+    #[must_use]
+    pub fn set_continue_on_error(mut self, enabled: bool) -> Self {
+        self.continue_on_error = enabled;
+        self
+    }
+
     #[must_use]
     pub fn copy_static(mut self, src: impl Into<String>, dest: impl Into<String>) -> Self {
         self.copied.push((dest.into(), src.into()));
         self
     }
 
+    /// Stores the output of a task in the cache directory, so that a cold
+    /// [`Website::build`] can skip it when nothing it depends on has changed.
+    ///
+    /// Persisted outputs are discarded whenever the site executable is
+    /// rebuilt. Only persist tasks whose output is fully determined by their
+    /// dependencies and the files they watch, loaders provided by this crate
+    /// fingerprint their input files automatically.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// let styles = config.load_css().entry("styles/**/*.scss")?.register();
+    /// config.persist(styles);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn persist<H: Persistent>(&mut self, handle: H) {
+        self.persisted.insert(handle.index(), handle.codec());
+    }
+
+    /// Compares the output of a task with the previous one every time it is
+    /// re-executed, dependent tasks only re-run when the output actually
+    /// changed.
+    ///
+    /// This is useful for tasks whose inputs change often without affecting
+    /// the result, for example templates or collections of metadata.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// let title = config.task().run(|_| Ok(String::from("My site")));
+    /// config.cutoff(title);
+    /// ```
+    pub fn cutoff<H: Cutoff>(&mut self, handle: H) {
+        self.cutoff.insert(handle.index(), handle.hasher());
+    }
+
+    /// Registers a callback that runs before any task is executed, both in
+    /// [`Website::build`] and in every rebuild in watch mode.
+    ///
+    /// An error returned from the callback aborts the build and is reported
+    /// as [`BuildError::Hook`](crate::error::BuildError::Hook).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// config.on_before_build(|_| {
+    ///     std::fs::create_dir_all(".cache/remote")?;
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn on_before_build<F>(&mut self, callback: F)
+    where
+        F: Fn(&Environment<G>) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.before_build.push(Box::new(callback));
+    }
+
+    /// Registers a callback that runs once every task has finished, before
+    /// anything is written to `dist`. The callback can inspect the files that
+    /// are about to be written through the [`SnapshotView`].
+    ///
+    /// An error returned from the callback prevents the snapshot from being
+    /// committed and is reported as
+    /// [`BuildError::Hook`](crate::error::BuildError::Hook).
+    pub fn on_after_tasks<F>(&mut self, callback: F)
+    where
+        F: Fn(&Environment<G>, &SnapshotView<'_>) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.after_tasks.push(Box::new(callback));
+    }
+
+    /// Registers a callback that runs after the snapshot was committed to
+    /// `dist`, for example to invoke a deploy script or write a build stamp.
+    ///
+    /// An error returned from the callback is reported as
+    /// [`BuildError::Hook`](crate::error::BuildError::Hook).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// config.on_after_commit(|_, report| {
+    ///     for file in report.written() {
+    ///         println!("purge /{}", file.path);
+    ///     }
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn on_after_commit<F>(&mut self, callback: F)
+    where
+        F: Fn(&Environment<G>, &CommitReport) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.after_commit.push(Box::new(callback));
+    }
+
     pub fn task(&mut self) -> TaskDef<'_, G> {
         TaskDef {
             blueprint: self,
             name: None,
+            options: TaskOptions::default(),
         }
     }
 
@@ -91,6 +250,31 @@ impl<G: Send + Sync + 'static> Blueprint<G> {
             out_dir: self.out_dir,
             cache_dir: self.cache_dir,
             progress: self.progress,
+            continue_on_error: self.continue_on_error,
+            persisted: self.persisted,
+            cutoff: self.cutoff,
+            limits: resource_classes(self.limits),
+            timeouts: self.timeouts,
+            hooks: self.hooks,
+            stats: Arc::default(),
+            timings: Arc::default(),
+            staging: false,
+            vfs: self.vfs,
+            #[cfg(feature = "live")]
+            server: self.server,
+            #[cfg(feature = "live")]
+            stylesheets: self.stylesheets,
+        }
+    }
+
+    /// Applies the options collected by a task builder, see [`TaskDef::limit`]
+    /// and [`TaskDef::timeout`].
+    pub(crate) fn set_options(&mut self, index: NodeIndex, options: TaskOptions) {
+        if let Some(limit) = options.limit {
+            self.limits.insert(index, limit);
+        }
+        if let Some(timeout) = options.timeout {
+            self.timeouts.insert(index, timeout);
         }
     }
 
@@ -125,6 +309,27 @@ impl<G: Send + Sync + 'static> Blueprint<G> {
     }
 }
 
+/// Creates one shared [`ResourceClass`] per class name. When tasks declare
+/// different limits for the same class, the smallest one is used.
+fn resource_classes(limits: HashMap<NodeIndex, Limit>) -> HashMap<NodeIndex, Arc<ResourceClass>> {
+    let mut max = HashMap::<Cow<'static, str>, usize>::new();
+    for limit in limits.values() {
+        max.entry(limit.class.clone())
+            .and_modify(|max| *max = (*max).min(limit.max))
+            .or_insert(limit.max);
+    }
+
+    let classes: HashMap<_, _> = max
+        .into_iter()
+        .map(|(class, max)| (class.clone(), Arc::new(ResourceClass::new(class, max))))
+        .collect();
+
+    limits
+        .into_iter()
+        .map(|(index, limit)| (index, classes[&limit.class].clone()))
+        .collect()
+}
+
 impl<G: Send + Sync> Default for Blueprint<G> {
     fn default() -> Self {
         Self {
@@ -133,6 +338,17 @@ impl<G: Send + Sync> Default for Blueprint<G> {
             out_dir: Utf8PathBuf::from("dist"),
             cache_dir: Utf8PathBuf::from(".cache"),
             progress: ProgressStyles::default(),
+            continue_on_error: false,
+            persisted: HashMap::default(),
+            cutoff: HashMap::default(),
+            limits: HashMap::default(),
+            timeouts: HashMap::default(),
+            hooks: Hooks::default(),
+            vfs: Arc::new(Disk),
+            #[cfg(feature = "live")]
+            server: ServerConfig::default(),
+            #[cfg(feature = "live")]
+            stylesheets: HashSet::default(),
         }
     }
 }
@@ -177,6 +393,14 @@ where
     }
 }
 
+/// Scheduling options collected by task builders and loaders, applied to the
+/// task once it is added to the graph.
+#[derive(Default)]
+pub(crate) struct TaskOptions {
+    pub limit: Option<Limit>,
+    pub timeout: Option<Duration>,
+}
+
 /// Entry point for defining a new task. Created by [`Blueprint::task`].
 ///
 /// Chain `.glob()`, `.each()`, `.using()`, or `.run()` to configure the task
@@ -185,6 +409,7 @@ where
 pub struct TaskDef<'a, G: Send + Sync> {
     blueprint: &'a mut Blueprint<G>,
     name: Option<Cow<'static, str>>,
+    options: TaskOptions,
 }
 
 impl<'a, G: Send + Sync + 'static> TaskDef<'a, G> {
@@ -193,6 +418,57 @@ impl<'a, G: Send + Sync + 'static> TaskDef<'a, G> {
         self
     }
 
+    /// Assigns the task to a resource class, at most `max` tasks of the
+    /// class run at the same time.
+    ///
+    /// Tasks of the same class also share a thread pool of `max` threads,
+    /// which bounds the parallel loops inside of them. Use it for memory
+    /// hungry work such as image encoding or external processes. If tasks
+    /// declare different limits for the same class, the smallest one wins.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// let report = config.task().limit("heavy", 2).run(|_| Ok(String::from("done")));
+    /// ```
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the task if it runs longer than `timeout`, measured from the
+    /// moment its body starts.
+    ///
+    /// Cancellation is cooperative, once the timeout elapses the
+    /// [`CancelToken`](crate::CancelToken) of the task is cancelled, and
+    /// child processes awaited with [`CancelToken::wait`](crate::CancelToken::wait)
+    /// are killed. The task is then reported as
+    /// [`BuildError::Timeout`](crate::error::BuildError::Timeout).
+    ///
+    /// A timeout only takes effect if the task checks its token, or waits for
+    /// child processes with [`CancelToken::wait`](crate::CancelToken::wait).
+    /// A task that does neither runs to completion, and is reported as timed
+    /// out afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use std::time::Duration;
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// let report = config
+    ///     .task()
+    ///     .timeout(Duration::from_secs(30))
+    ///     .run(|ctx| {
+    ///         ctx.cancel_token().check()?;
+    ///         Ok(String::from("done"))
+    ///     });
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     /// Load assets from file system using glob pattern.
     pub fn glob(self, glob: impl Into<String>) -> Result<TaskBinderGlob<'a, G>, HauchiwaError> {
         let glob = glob.into();
@@ -200,6 +476,7 @@ impl<'a, G: Send + Sync + 'static> TaskDef<'a, G> {
         Ok(TaskBinderGlob {
             blueprint: self.blueprint,
             name: self.name,
+            options: self.options,
             entry: vec![glob],
             watch: vec![pattern],
         })
@@ -210,6 +487,7 @@ impl<'a, G: Send + Sync + 'static> TaskDef<'a, G> {
         TaskBinderEach {
             blueprint: self.blueprint,
             name: self.name,
+            options: self.options,
             primary: each,
             secondary: (),
         }
@@ -223,6 +501,7 @@ impl<'a, G: Send + Sync + 'static> TaskDef<'a, G> {
         TaskBinder {
             blueprint: self.blueprint,
             name: self.name,
+            options: self.options,
             dependencies,
         }
     }
@@ -233,12 +512,14 @@ impl<'a, G: Send + Sync + 'static> TaskDef<'a, G> {
         F: Fn(&TaskContext<'_, G>) -> anyhow::Result<R> + Send + Sync + 'static,
         R: Send + Sync + 'static,
     {
-        self.blueprint.add_task_coarse(NodeGather {
+        let handle = self.blueprint.add_task_coarse(NodeGather {
             name: self.name.unwrap_or(type_name::<F>().into()),
             dependencies: (),
             callback: move |ctx, ()| callback(ctx),
             _phantom: PhantomData,
-        })
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 }
 
@@ -250,6 +531,7 @@ impl<'a, G: Send + Sync + 'static> TaskDef<'a, G> {
 pub struct TaskBinderGlob<'a, G: Send + Sync> {
     blueprint: &'a mut Blueprint<G>,
     name: Option<Cow<'static, str>>,
+    options: TaskOptions,
     entry: Vec<String>,
     watch: Vec<Pattern>,
 }
@@ -260,6 +542,19 @@ impl<'a, G: Send + Sync + 'static> TaskBinderGlob<'a, G> {
         self
     }
 
+    /// Assigns the task to a resource class, at most `max` tasks of the
+    /// class run at the same time. See [`TaskDef::limit`].
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the task if it runs longer than `timeout`. See [`TaskDef::timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     pub fn glob(mut self, glob: impl Into<String>) -> Result<Self, HauchiwaError> {
         let glob = glob.into();
         let pattern = Pattern::new(&glob)?;
@@ -268,19 +563,28 @@ impl<'a, G: Send + Sync + 'static> TaskBinderGlob<'a, G> {
         Ok(self)
     }
 
+    /// Processes each matched file with `callback`, producing a [`Many`]
+    /// keyed by the path of the file.
+    ///
+    /// On a rebuild only new and edited files are passed to `callback`. Files
+    /// whose content hash didn't change keep the item from the previous run,
+    /// which is why `R` has to implement [`Clone`].
     pub fn map<F, R>(self, callback: F) -> Many<R>
     where
         F: Fn(&TaskContext<G>, &mut Store, Input) -> anyhow::Result<R> + Send + Sync + 'static,
-        R: Send + Sync + 'static,
+        R: Send + Sync + Clone + 'static,
     {
         let task =
             crate::loader::GlobFiles::new(self.entry, self.watch, move |ctx, store, input| {
                 let path = input.path.clone();
                 let res = callback(ctx, store, input)?;
                 Ok((path, res))
-            });
+            })
+            .with_name(self.name);
 
-        self.blueprint.add_task_fine(task)
+        let handle = self.blueprint.add_task_fine(task);
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 }
 
@@ -295,6 +599,7 @@ where
 {
     blueprint: &'a mut Blueprint<G>,
     name: Option<Cow<'static, str>>,
+    options: TaskOptions,
     primary: Many<T>,
     secondary: D,
 }
@@ -310,6 +615,19 @@ where
         self
     }
 
+    /// Assigns the task to a resource class, at most `max` tasks of the
+    /// class run at the same time. See [`TaskDef::limit`].
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the task if it runs longer than `timeout`. See [`TaskDef::timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     /// Add secondary dependencies (context) that are needed for every item mapping.
     pub fn using<D2>(self, dependencies: D2) -> TaskBinderEach<'a, G, T, D2>
     where
@@ -318,6 +636,7 @@ where
         TaskBinderEach {
             blueprint: self.blueprint,
             name: self.name,
+            options: self.options,
             primary: self.primary,
             secondary: dependencies,
         }
@@ -333,13 +652,180 @@ where
             + 'static,
         R: Send + Sync + Clone + 'static,
     {
-        self.blueprint.add_task_fine(NodeMap {
+        let handle = self.blueprint.add_task_fine(NodeMap {
             name: self.name.unwrap_or(type_name::<F>().into()),
             dep_primary: self.primary,
             dep_secondary: self.secondary,
             callback,
             _phantom: PhantomData,
-        })
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
+    }
+}
+
+impl<'a, G, T> TaskBinderEach<'a, G, T, ()>
+where
+    G: Send + Sync + 'static,
+    T: Send + Sync + Clone + 'static,
+{
+    /// Keep only the items for which the predicate returns `true`.
+    ///
+    /// Items keep their keys, tasks reading the result are not affected by
+    /// edits to items that are filtered out.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// # let posts = config.task().using(()).spread(|_, _| Ok(vec![("a".to_string(), false)]));
+    /// let published = config.task().each(posts).filter(|draft| !draft);
+    /// ```
+    pub fn filter<F>(self, predicate: F) -> Many<T>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        let handle = self.blueprint.add_task_fine(NodeFilter {
+            name: self.name.unwrap_or(type_name::<F>().into()),
+            dependency: self.primary,
+            predicate,
+            _phantom: PhantomData,
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
+    }
+
+    /// Order the items by a key extracted from each item.
+    ///
+    /// The resulting collection is keyed by the zero-padded position of each
+    /// item (`00000000`, `00000001`, ...), so iterating over it yields the
+    /// items in sorted order. The sort is stable. A position is only
+    /// invalidated when a different item moves into it or the item changes.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// # let posts = config.task().using(()).spread(|_, _| Ok(vec![("a".to_string(), 2024)]));
+    /// let newest = config.task().each(posts).sort_by_key(|year| std::cmp::Reverse(*year));
+    /// ```
+    pub fn sort_by_key<F, K>(self, key: F) -> Many<T>
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Ord + 'static,
+    {
+        let handle = self.blueprint.add_task_fine(NodeSort {
+            name: self.name.unwrap_or(type_name::<F>().into()),
+            dependency: self.primary,
+            key,
+            _phantom: PhantomData,
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
+    }
+
+    /// Collect the items into groups.
+    ///
+    /// The callback returns every group an item belongs to, along with the
+    /// value stored for it in that group. Groups are keyed by name and hold
+    /// their values in the order of the source keys. A group is only
+    /// invalidated when its values change, so by storing only what a group
+    /// page needs, editing unrelated parts of an item doesn't rebuild it.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// # let posts = config.task().using(()).spread(|_, _| Ok(vec![
+    /// #     ("a".to_string(), (String::from("Title"), vec![String::from("rust")])),
+    /// # ]));
+    /// // tag -> titles of the posts with that tag
+    /// let tags = config.task().each(posts).group_by(|(title, tags)| {
+    ///     tags.iter().map(|tag| (tag.clone(), title.clone())).collect()
+    /// });
+    /// ```
+    pub fn group_by<F, R>(self, callback: F) -> Many<Vec<R>>
+    where
+        F: Fn(&T) -> Vec<(String, R)> + Send + Sync + 'static,
+        R: Send + Sync + std::hash::Hash + Clone + 'static,
+    {
+        let handle = self.blueprint.add_task_fine(NodeGroup {
+            name: self.name.unwrap_or(type_name::<F>().into()),
+            dependency: self.primary,
+            callback,
+            previous: Mutex::default(),
+            _phantom: PhantomData,
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
+    }
+
+    /// Sort the items by a key and split them into [`Page`]s of `per_page`
+    /// items each, keyed by the zero-padded page number (`00000001`,
+    /// `00000002`, ...), so iterating over them yields the pages in order.
+    ///
+    /// Each page knows the numbers of its neighbours, which is enough to
+    /// render `/page/2/` style listings with prev/next links. Editing an item
+    /// only invalidates the page holding it, unless the edit changes the
+    /// order. An empty collection produces a single empty page. The task
+    /// fails if `per_page` is 0.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// # let posts = config.task().using(()).spread(|_, _| Ok(vec![("a".to_string(), 2024)]));
+    /// let pages = config.task().each(posts).paginate(10, |year| std::cmp::Reverse(*year));
+    /// ```
+    pub fn paginate<F, K>(self, per_page: usize, key: F) -> Many<Page<T>>
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Ord + 'static,
+    {
+        let handle = self.blueprint.add_task_fine(NodePaginate {
+            name: self.name.unwrap_or(type_name::<F>().into()),
+            dependency: self.primary,
+            per_page,
+            key,
+            _phantom: PhantomData,
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
+    }
+
+    /// Pair each item with the item of `other` stored under the key returned
+    /// by `key`.
+    ///
+    /// Items whose key is missing from `other` are left out. Pairs keep the
+    /// keys of this collection and are only invalidated when either of the
+    /// two items changes.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// # let posts = config.task().using(()).spread(|_, _| Ok(vec![
+    /// #     ("a".to_string(), String::from("kamoshi")),
+    /// # ]));
+    /// # let authors = config.task().using(()).spread(|_, _| Ok(vec![
+    /// #     ("kamoshi".to_string(), String::from("Maciej")),
+    /// # ]));
+    /// let posts_with_authors = config.task().each(posts).join(authors, |author| author.clone());
+    /// ```
+    pub fn join<U, F>(self, other: Many<U>, key: F) -> Many<(T, U)>
+    where
+        U: Send + Sync + Clone + 'static,
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        let handle = self.blueprint.add_task_fine(NodeJoin {
+            name: self.name.unwrap_or(type_name::<F>().into()),
+            left: self.primary,
+            right: other,
+            key,
+            _phantom: PhantomData,
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 }
 
@@ -351,6 +837,7 @@ where
 pub struct TaskBinder<'a, G: Send + Sync, D> {
     blueprint: &'a mut Blueprint<G>,
     name: Option<Cow<'static, str>>,
+    options: TaskOptions,
     dependencies: D,
 }
 
@@ -364,6 +851,19 @@ where
         self
     }
 
+    /// Assigns the task to a resource class, at most `max` tasks of the
+    /// class run at the same time. See [`TaskDef::limit`].
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the task if it runs longer than `timeout`. See [`TaskDef::timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     pub fn merge<F, R>(self, callback: F) -> One<R>
     where
         F: for<'b> Fn(&TaskContext<'b, G>, D::Output<'b>) -> anyhow::Result<R>
@@ -372,12 +872,14 @@ where
             + 'static,
         R: Send + Sync + 'static,
     {
-        self.blueprint.add_task_coarse(NodeGather {
+        let handle = self.blueprint.add_task_coarse(NodeGather {
             name: self.name.unwrap_or(type_name::<F>().into()),
             dependencies: self.dependencies,
             callback,
             _phantom: PhantomData,
-        })
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 
     pub fn spread<F, R>(self, callback: F) -> Many<R>
@@ -388,12 +890,74 @@ where
             + 'static,
         R: Send + Sync + std::hash::Hash + 'static,
     {
-        self.blueprint.add_task_fine(NodeScatter {
+        let handle = self.blueprint.add_task_fine(NodeScatter {
             name: self.name.unwrap_or(type_name::<F>().into()),
             dependencies: self.dependencies,
             callback,
             _phantom: PhantomData,
-        })
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
+    }
+
+    /// Like [`spread`](Self::spread), but the collection is produced one key
+    /// at a time, which makes it incremental.
+    ///
+    /// The `keys` callback lists the keys of the collection, then `callback`
+    /// is called once for every key. Each call is tracked separately, just
+    /// like a task of its own, so when the dependencies change only the keys
+    /// whose accessed inputs changed are regenerated. This is useful for
+    /// pages such as tag listings, where each page reads only a small part of
+    /// the input.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// # let posts = config.task().using(()).spread(|_, _| Ok(vec![
+    /// #     ("posts/rust/intro".to_string(), String::from("Intro")),
+    /// # ]));
+    /// // one listing per tag, each reads only the posts with that tag
+    /// let tags = config.task().using(posts).spread_keyed(
+    ///     |_, posts| {
+    ///         let mut tags: Vec<_> = posts
+    ///             .iter()
+    ///             .filter_map(|(key, _)| key.split('/').nth(1))
+    ///             .collect();
+    ///         tags.dedup();
+    ///         Ok(tags.into_iter().map(String::from).collect())
+    ///     },
+    ///     |_, tag, posts| {
+    ///         let titles: Vec<_> = posts
+    ///             .glob(format!("posts/{tag}/*"))?
+    ///             .map(|(_, title)| title.as_str())
+    ///             .collect();
+    ///         Ok(titles.join("\n"))
+    ///     },
+    /// );
+    /// ```
+    pub fn spread_keyed<K, F, R>(self, keys: K, callback: F) -> Many<R>
+    where
+        K: for<'b> Fn(&TaskContext<'b, G>, D::Output<'b>) -> anyhow::Result<Vec<String>>
+            + Send
+            + Sync
+            + 'static,
+        F: for<'b> Fn(&TaskContext<'b, G>, &str, D::Output<'b>) -> anyhow::Result<R>
+            + Send
+            + Sync
+            + 'static,
+        R: Send + Sync + std::hash::Hash + Clone + 'static,
+    {
+        let handle = self.blueprint.add_task_fine(NodeScatterKeyed {
+            name: self.name.unwrap_or(type_name::<F>().into()),
+            dependencies: self.dependencies,
+            keys,
+            callback,
+            previous: Mutex::default(),
+            _phantom: PhantomData,
+        });
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 }
 
@@ -408,13 +972,31 @@ pub struct Website<G: Send + Sync = ()> {
     pub(crate) out_dir: Utf8PathBuf,
     pub(crate) cache_dir: Utf8PathBuf,
     pub(crate) progress: ProgressStyles,
+    pub(crate) continue_on_error: bool,
+    pub(crate) persisted: HashMap<NodeIndex, Arc<dyn Codec>>,
+    pub(crate) cutoff: HashMap<NodeIndex, OutputHasher>,
+    pub(crate) limits: HashMap<NodeIndex, Arc<ResourceClass>>,
+    pub(crate) timeouts: HashMap<NodeIndex, Duration>,
+    pub(crate) hooks: Hooks<G>,
+    pub(crate) stats: Arc<StatCache>,
+    pub(crate) timings: Arc<Timings>,
+    /// Set during [`Website::build_dry`], assets saved by tasks are kept in
+    /// the cache directory instead of being published to `dist`.
+    pub(crate) staging: bool,
+    pub(crate) vfs: Arc<dyn Vfs>,
+    #[cfg(feature = "live")]
+    pub(crate) server: ServerConfig,
+    /// Tasks which only produce stylesheets, swapped in place by the
+    /// live-reload script when nothing else changed.
+    #[cfg(feature = "live")]
+    pub(crate) stylesheets: HashSet<NodeIndex>,
 }
 
 impl<G> Website<G>
 where
     G: Send + Sync + 'static,
 {
-    fn run_preflight(&self) -> Result<(), crate::error::HauchiwaError> {
+    pub(crate) fn run_preflight(&self) -> Result<(), crate::error::HauchiwaError> {
         use std::collections::HashSet;
         let mut seen = HashSet::new();
         let mut missing = Vec::new();
@@ -447,6 +1029,10 @@ where
     /// 3. Execute the task graph in parallel.
     /// 4. Save the generated [`Output`](crate::Output)s to `dist`.
     ///
+    /// The hooks registered with [`Blueprint::on_before_build`],
+    /// [`Blueprint::on_after_tasks`] and [`Blueprint::on_after_commit`] run
+    /// between these steps.
+    ///
     /// # Arguments
     ///
     /// * `data` - The global user data to pass to all tasks.
@@ -463,29 +1049,250 @@ where
 
         let prev_meta =
             crate::snapshot::SnapshotMeta::load(&self.cache_dir).map_err(BuildError::Io)?;
-        let static_files = crate::utils::collect_static(&self.copied, &self.out_dir)?;
+        let static_files = crate::utils::collect_static(&self.copied, &self.out_dir, &*self.vfs)?;
 
-        let (_, mut manifest, diagnostics) = run_once_parallel(self, &globals)?;
+        self.hooks.before_build(&globals)?;
+
+        let (_, mut manifest, mut diagnostics) = run_once_parallel(self, &globals)?;
+        if !diagnostics.failures.is_empty() {
+            return Err(BuildError::Tasks(diagnostics.failures).into());
+        }
 
         for entry in &static_files {
-            manifest.insert_static_file(entry.dist_rel.clone(), entry.source_utf8.clone())?;
+            manifest.insert_static_file(entry.dist_rel.clone(), entry.source.clone())?;
         }
-        crate::utils::copy_static_entries(&static_files, &self.progress.copy)?;
+        crate::utils::copy_static_entries(&static_files, &self.progress.copy, &*self.vfs)?;
 
-        match prev_meta {
+        self.hooks
+            .after_tasks(&globals, &SnapshotView::new(&manifest))?;
+
+        diagnostics.commit = match prev_meta {
             Some(ref prev) => manifest
                 .commit_diff_meta(prev, &self.out_dir)
                 .map_err(BuildError::Io)?,
             None => manifest.commit(&self.out_dir).map_err(BuildError::Io)?,
-        }
+        };
+        tracing::info!("dist: {}", diagnostics.commit);
         manifest
             .to_meta()
             .save(&self.cache_dir)
             .map_err(BuildError::Io)?;
 
+        self.hooks.after_commit(&globals, &diagnostics.commit)?;
+
+        Ok(diagnostics)
+    }
+
+    /// Runs the build process once, but only for the outputs selected by
+    /// `filter`, leaving every other file in `dist` alone.
+    ///
+    /// Tasks are selected using the snapshot of the previous build: only the
+    /// tasks which produced a matching output, and everything they depend
+    /// on, are executed. Outputs of these tasks which don't match the filter
+    /// are not written. Every task is executed, with a warning, when there's
+    /// no previous build, when none of its outputs matched, or when the site
+    /// changed since. Only the static files matching the filter are copied.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The global user data to pass to all tasks.
+    /// * `filter` - The outputs to build, by their path in `dist`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use hauchiwa::OutputFilter;
+    ///
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// let mut website = config.finish();
+    /// website.build_filtered((), OutputFilter::href("/posts/hello/")?)?;
+    /// # Ok::<(), hauchiwa::error::HauchiwaError>(())
+    /// ```
+    pub fn build_filtered(
+        &mut self,
+        data: G,
+        filter: OutputFilter,
+    ) -> Result<Diagnostics, crate::error::HauchiwaError> {
+        use crate::error::BuildError;
+        self.run_preflight()?;
+
+        let globals = Environment {
+            generator: "hauchiwa",
+            mode: Mode::Build,
+            port: None,
+            data,
+        };
+
+        let prev_meta =
+            crate::snapshot::SnapshotMeta::load(&self.cache_dir).map_err(BuildError::Io)?;
+
+        let producers = prev_meta.as_ref().map(|prev| prev.producers_of(&filter));
+        // Indices from the previous build only refer to the same tasks if the
+        // graph didn't change, the names of the tasks are a cheap check.
+        let stale = producers.iter().flatten().any(|&(index, name)| {
+            self.graph
+                .node_weight(index)
+                .is_none_or(|task| task.name() != name)
+        });
+        let targets: Vec<_> = match producers {
+            Some(producers) if !producers.is_empty() && !stale => {
+                producers.into_iter().map(|(index, _)| index).collect()
+            }
+            producers => {
+                let reason = match producers {
+                    None => "there is no previous build",
+                    Some(producers) if producers.is_empty() => {
+                        "no output of the previous build matches the filter"
+                    }
+                    Some(_) => "the site changed since the previous build",
+                };
+                tracing::warn!("filtered build: {reason}, running all tasks");
+                self.graph
+                    .node_indices()
+                    .filter(|&i| self.graph[i].is_output())
+                    .collect()
+            }
+        };
+        let pending = find_required(self, targets);
+        tracing::info!(
+            "filtered build: running {} of {} tasks",
+            pending.len(),
+            self.graph.node_count()
+        );
+
+        let mut static_files =
+            crate::utils::collect_static(&self.copied, &self.out_dir, &*self.vfs)?;
+        static_files.retain(|entry| filter.matches(&entry.dist_rel));
+
+        self.hooks.before_build(&globals)?;
+
+        let (_, mut manifest, mut diagnostics) = run_subgraph_parallel(self, &globals, pending)?;
+        if !diagnostics.failures.is_empty() {
+            return Err(BuildError::Tasks(diagnostics.failures).into());
+        }
+
+        manifest.retain_pages(|path| filter.matches(path));
+        for entry in &static_files {
+            manifest.insert_static_file(entry.dist_rel.clone(), entry.source.clone())?;
+        }
+        crate::utils::copy_static_entries(&static_files, &self.progress.copy, &*self.vfs)?;
+
+        self.hooks
+            .after_tasks(&globals, &SnapshotView::new(&manifest))?;
+
+        diagnostics.commit = manifest
+            .commit_filtered(prev_meta.as_ref(), &self.out_dir, &filter)
+            .map_err(BuildError::Io)?;
+        tracing::info!("dist: {}", diagnostics.commit);
+
+        let mut meta = prev_meta.unwrap_or_default();
+        meta.update(manifest.to_meta(), &diagnostics.commit.removed);
+        meta.save(&self.cache_dir).map_err(BuildError::Io)?;
+
+        self.hooks.after_commit(&globals, &diagnostics.commit)?;
+
+        Ok(diagnostics)
+    }
+
+    /// Runs the build process once without modifying `dist`, and reports what
+    /// [`build`](Self::build) would write to it and delete from it in
+    /// [`Diagnostics::commit`].
+    ///
+    /// Neither static files nor the snapshot kept in the cache directory are
+    /// touched, and no hooks are run. Content-addressed assets saved with
+    /// [`Store::save`](crate::Store::save) are staged in the cache directory,
+    /// the next regular build publishes them.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The global user data to pass to all tasks.
+    pub fn build_dry(&mut self, data: G) -> Result<Diagnostics, crate::error::HauchiwaError> {
+        use crate::error::BuildError;
+        use crate::snapshot::Baseline;
+
+        let prev_meta =
+            crate::snapshot::SnapshotMeta::load(&self.cache_dir).map_err(BuildError::Io)?;
+        let (manifest, mut diagnostics) = self.run_staged(data)?;
+
+        let baseline = match prev_meta {
+            Some(ref prev) => Baseline::Meta(prev),
+            None => Baseline::Disk,
+        };
+        diagnostics.commit = manifest
+            .diff(baseline, &self.out_dir)
+            .map_err(BuildError::Io)?;
+        tracing::info!("dist (dry run): {}", diagnostics.commit);
+
         Ok(diagnostics)
     }
 
+    /// Runs the build process once and returns every file it produced,
+    /// without writing anything to `dist`.
+    ///
+    /// The returned [`BuiltSite`] maps each dist-relative path to its content
+    /// and to the task or static file that produced it. This is meant for
+    /// integration tests and for serving a site straight from memory. Like
+    /// [`build_dry`](Self::build_dry), no hooks are run and assets saved with
+    /// [`Store::save`](crate::Store::save) are staged in the cache directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The global user data to pass to all tasks.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # let mut config = hauchiwa::Blueprint::<()>::new();
+    /// let site = config.finish().build_in_memory(())?;
+    /// assert!(site.text("index.html").is_some_and(|html| html.contains("<h1>")));
+    /// # Ok::<(), hauchiwa::error::HauchiwaError>(())
+    /// ```
+    pub fn build_in_memory(&mut self, data: G) -> Result<BuiltSite, crate::error::HauchiwaError> {
+        use crate::error::BuildError;
+
+        let (manifest, _) = self.run_staged(data)?;
+        let site = manifest
+            .into_built(&self.out_dir, &self.cache_dir, &*self.vfs)
+            .map_err(BuildError::Io)?;
+
+        Ok(site)
+    }
+
+    /// Runs the task graph with assets staged in the cache directory and
+    /// collects the snapshot, including static files, without copying them.
+    fn run_staged(
+        &mut self,
+        data: G,
+    ) -> Result<(Snapshot, Diagnostics), crate::error::HauchiwaError> {
+        use crate::error::BuildError;
+
+        self.run_preflight()?;
+
+        let globals = Environment {
+            generator: "hauchiwa",
+            mode: Mode::Build,
+            port: None,
+            data,
+        };
+
+        let static_files = crate::utils::collect_static(&self.copied, &self.out_dir, &*self.vfs)?;
+
+        self.staging = true;
+        let result = run_once_parallel(self, &globals);
+        self.staging = false;
+
+        let (_, mut manifest, diagnostics) = result?;
+        if !diagnostics.failures.is_empty() {
+            return Err(BuildError::Tasks(diagnostics.failures).into());
+        }
+
+        for entry in &static_files {
+            manifest.insert_static_file(entry.dist_rel.clone(), entry.source.clone())?;
+        }
+
+        Ok((manifest, diagnostics))
+    }
+
     /// Starts the development server in watch mode.
     ///
     /// This will perform an initial build and then watch for file changes.
@@ -508,3 +1315,194 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::Output;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_build_dry_leaves_dist_untouched() {
+        let root = TempDir::new("dry");
+
+        let mut config = Blueprint::<()>::new()
+            .set_dir_dist(root.join("dist"))
+            .set_dir_cache(root.join("cache"));
+        config
+            .task()
+            .glob("Cargo.toml")
+            .unwrap()
+            .map(|_, store, _| Ok(store.save(b"body {}", "css")?));
+        config
+            .task()
+            .run(|_| Ok(Output::html("index", "<h1>Hello</h1>")));
+        let mut site = config.finish();
+
+        let diagnostics = site.build_dry(()).unwrap();
+        let mut new: Vec<_> = diagnostics
+            .commit
+            .new
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect();
+        new.sort();
+
+        assert_eq!(new.len(), 2);
+        assert!(new[0].starts_with("hash/"));
+        assert_eq!(new[1], "index.html");
+        assert!(!root.join("dist").exists());
+        assert!(!root.join("cache/snapshot").exists());
+
+        site.build(()).unwrap();
+        let diagnostics = site.build_dry(()).unwrap();
+
+        assert!(diagnostics.commit.is_empty());
+        assert_eq!(diagnostics.commit.unchanged.len(), 2);
+    }
+
+    #[test]
+    fn test_build_in_memory() {
+        let root = TempDir::new("memory");
+
+        let mut config = Blueprint::<()>::new()
+            .set_dir_dist(root.join("dist"))
+            .set_dir_cache(root.join("cache"));
+        let assets = config
+            .task()
+            .glob("Cargo.toml")
+            .unwrap()
+            .map(|_, store, _| Ok(store.save(b"body {}", "css")?));
+        config
+            .task()
+            .name("index")
+            .using(assets)
+            .merge(|_, assets| {
+                let href = assets.values().next().unwrap();
+                Ok(Output::html("index", format!("<link href={href}>")))
+            });
+        let site = config.finish().build_in_memory(()).unwrap();
+
+        let html = site.text("/index.html").unwrap();
+        let href = html.trim_start_matches("<link href=").trim_end_matches('>');
+        assert_eq!(site.bytes(href), Some(&b"body {}"[..]));
+        assert_eq!(
+            site.get("index.html").unwrap().producer.task(),
+            Some("index")
+        );
+        assert_eq!(site.len(), 2);
+        assert!(!root.join("dist").exists());
+    }
+
+    #[test]
+    fn test_build_from_memory_vfs() {
+        let root = TempDir::new("vfs");
+
+        let files = crate::vfs::MemoryFs::new()
+            .with("content/a.md", "a")
+            .with("content/b.md", "b")
+            .with("static/fonts/x.woff2", "font");
+        let mut config = Blueprint::<()>::new()
+            .set_dir_dist(root.join("dist"))
+            .set_dir_cache(root.join("cache"))
+            .set_vfs(files)
+            .copy_static("static", "static");
+        let posts = config
+            .task()
+            .glob("content/*.md")
+            .unwrap()
+            .map(|_, _, input| Ok(String::from_utf8(input.read()?.into())?));
+        config.task().using(posts).merge(|_, posts| {
+            let body: String = posts.values().cloned().collect();
+            Ok(Output::html("index", body))
+        });
+        let mut site = config.finish();
+
+        site.build(()).unwrap();
+
+        let dist = root.join("dist");
+        assert_eq!(
+            std::fs::read_to_string(dist.join("index.html")).unwrap(),
+            "ab"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dist.join("static/fonts/x.woff2")).unwrap(),
+            "font"
+        );
+    }
+
+    #[test]
+    fn test_build_filtered() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let root = TempDir::new("filtered");
+
+        static INDEX: AtomicUsize = AtomicUsize::new(0);
+        static ABOUT: AtomicUsize = AtomicUsize::new(0);
+
+        let mut config = Blueprint::<()>::new()
+            .set_dir_dist(root.join("dist"))
+            .set_dir_cache(root.join("cache"));
+        let title = config.task().run(|_| Ok(String::from("title")));
+        config.task().name("index").using(title).merge(|_, title| {
+            INDEX.fetch_add(1, Ordering::SeqCst);
+            Ok(Output::html("index", title.clone()))
+        });
+        config.task().name("about").using(title).merge(|_, title| {
+            let n = ABOUT.fetch_add(1, Ordering::SeqCst);
+            Ok(Output::html("about", format!("{title} {n}")))
+        });
+        let mut site = config.finish();
+
+        site.build(()).unwrap();
+        let dist = root.join("dist");
+        std::fs::write(dist.join("unrelated.txt"), "keep").unwrap();
+
+        let filter = OutputFilter::href("/about/").unwrap();
+        let diagnostics = site.build_filtered((), filter).unwrap();
+
+        assert_eq!(INDEX.load(Ordering::SeqCst), 1);
+        assert_eq!(ABOUT.load(Ordering::SeqCst), 2);
+        assert_eq!(diagnostics.commit.changed.len(), 1);
+        assert!(diagnostics.commit.removed.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(dist.join("about/index.html")).unwrap(),
+            "title 1"
+        );
+        assert!(dist.join("index.html").exists());
+        assert!(dist.join("unrelated.txt").exists());
+
+        // Pages outside the filter are still known to the next full build.
+        let diagnostics = site.build(()).unwrap();
+        assert_eq!(diagnostics.commit.unchanged.len(), 1);
+        assert_eq!(diagnostics.commit.removed.len(), 0);
+    }
+
+    #[test]
+    fn test_build_filtered_tells_apart_tasks_by_index() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let root = TempDir::new("filtered-index");
+
+        static RUNS: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+        let mut config = Blueprint::<()>::new()
+            .set_dir_dist(root.join("dist"))
+            .set_dir_cache(root.join("cache"));
+        for (i, name) in ["a", "b"].into_iter().enumerate() {
+            config.task().name("page").run(move |_| {
+                RUNS[i].fetch_add(1, Ordering::SeqCst);
+                Ok(Output::html(name, name))
+            });
+        }
+        let mut site = config.finish();
+
+        site.build(()).unwrap();
+        let filter = OutputFilter::href("/b/").unwrap();
+        site.build_filtered((), filter).unwrap();
+
+        assert_eq!(RUNS[0].load(Ordering::SeqCst), 1);
+        assert_eq!(RUNS[1].load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,15 +1,18 @@
 use std::fs;
 use std::io::{ErrorKind, Write};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::{any::Any, collections::BTreeMap};
 
 use camino::{Utf8Path, Utf8PathBuf};
 use serde::{Deserialize, Serialize};
 
-use crate::error::BuildError;
+use crate::engine::CancelToken;
+use crate::error::{BuildError, LoaderError};
+use crate::stat::StatCache;
+use crate::vfs::{Disk, Vfs};
 
 /// A type-erased, thread-safe container.
-pub(crate) type Dynamic = Arc<dyn Any + Send + Sync>;
+pub type Dynamic = Arc<dyn Any + Send + Sync>;
 
 /// Atomic reference-counted string type used for identifiers.
 pub(crate) type ArcStr = std::sync::Arc<str>;
@@ -21,7 +24,7 @@ pub(crate) type ArcStr = std::sync::Arc<str>;
 ///    if they are "dirty" and require rebuilding.
 /// 2. It generates unique filenames (e.g., inside `dist/hash/`) for assets like
 ///    images or scripts, ensuring effective browser caching.
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub(crate) struct Hash32([u8; 32]);
 
 impl<T> From<T> for Hash32
@@ -114,7 +117,9 @@ pub struct Environment<D: Send + Sync = ()> {
     pub generator: &'static str,
     /// The current build mode (Build or Watch).
     pub mode: Mode,
-    /// The port of the development server (if running).
+    /// The port pages connect to for live reload, in watch mode. With the
+    /// `server` feature this is the port of the HTTP server, otherwise see
+    /// [`ServerConfig::live_reload_port`](crate::ServerConfig::live_reload_port).
     pub port: Option<u16>,
     /// User-defined global data.
     pub data: D,
@@ -140,6 +145,10 @@ impl<G: Send + Sync> Environment<G> {
     /// If the site is running in `Watch` mode and a port is configured, this returns
     /// a script that connects to the WebSocket server to listen for reload events.
     ///
+    /// With the `server` feature this always returns `None`, the HTTP server
+    /// injects the script into every HTML page it serves in watch mode, so the
+    /// pages written to `dist` are the same as in a regular build.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
@@ -154,22 +163,18 @@ impl<G: Send + Sync> Environment<G> {
     /// });
     /// ```
     pub fn get_refresh_script(&self) -> Option<String> {
-        self.port.map(|port| {
-            format!(
-                r#"
-const socket = new WebSocket("ws://localhost:{port}");
-socket.addEventListener("message", event => {{
-    window.location.reload();
-}});
-"#
-            )
-        })
+        if cfg!(feature = "server") {
+            None
+        } else {
+            self.port
+                .map(|port| crate::server::refresh_script(Some(port)))
+        }
     }
 }
 
 /// This matches the browser's Import Map specification.
 /// <https://developer.mozilla.org/en-US/docs/Web/HTML/Element/script/type/importmap>
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
 pub struct ImportMap {
     imports: BTreeMap<String, String>,
 }
@@ -224,6 +229,20 @@ pub struct TaskContext<'a, G: Send + Sync = ()> {
     pub(crate) span: tracing::Span,
     /// Progress bar styles configured for this build.
     pub(crate) progress: &'a crate::utils::ProgressStyles,
+    /// Whether loaders should skip files that fail to load instead of failing.
+    pub(crate) continue_on_error: bool,
+    /// Cancelled when the task times out or the build is abandoned.
+    pub(crate) cancel: CancelToken,
+}
+
+impl<G: Send + Sync> TaskContext<'_, G> {
+    /// Returns the token signalling that this task should stop early.
+    ///
+    /// Long running tasks should check it between units of work, and wait
+    /// for any child processes they spawn with [`CancelToken::wait`].
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel
+    }
 }
 
 /// A helper for managing side effects and imports within a task.
@@ -241,6 +260,16 @@ pub struct Store {
     pub(crate) store_paths: Vec<Utf8PathBuf>,
     pub(crate) out_dir: Utf8PathBuf,
     pub(crate) cache_dir: Utf8PathBuf,
+    /// If set, assets are kept in `cache_dir` and never published to
+    /// `out_dir`, see [`Website::build_dry`](crate::Website::build_dry).
+    pub(crate) staging: bool,
+    /// Files that failed to load and were left out of the task's output.
+    /// Shared between clones, reported in `Diagnostics` after the task completes.
+    pub(crate) failures: Arc<Mutex<Vec<LoaderError>>>,
+    /// Content hashes of input files, shared by all tasks in a build.
+    pub(crate) stats: Arc<StatCache>,
+    /// The filesystem input files are read from.
+    pub(crate) vfs: Arc<dyn Vfs>,
 }
 
 impl Store {
@@ -258,11 +287,26 @@ impl Store {
             store_paths: Vec::new(),
             out_dir: out_dir.into(),
             cache_dir: cache_dir.into(),
+            staging: false,
+            failures: Arc::default(),
+            stats: Arc::default(),
+            vfs: Arc::new(Disk),
         }
     }
 
     pub(crate) fn fork(&self) -> Self {
-        Self::with_dirs(self.out_dir.clone(), self.cache_dir.clone())
+        Self {
+            staging: self.staging,
+            stats: self.stats.clone(),
+            vfs: self.vfs.clone(),
+            ..Self::with_dirs(self.out_dir.clone(), self.cache_dir.clone())
+        }
+    }
+
+    /// Records a file that failed to load, without failing the whole task.
+    pub(crate) fn report(&self, error: LoaderError) {
+        #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+        self.failures.lock().unwrap().push(error);
     }
 
     /// Saves raw data as a content-addressed artifact.
@@ -283,7 +327,6 @@ impl Store {
         let hash = hash.to_hex();
 
         let path_temp = self.cache_dir.join("hash").join(&hash);
-        let path_dist = self.out_dir.join("hash").join(&hash).with_extension(ext);
         let path_rel = Utf8Path::new("hash").join(&hash).with_extension(ext);
         let path_root = Utf8Path::new("/hash/").join(&hash).with_extension(ext);
 
@@ -298,14 +341,34 @@ impl Store {
             Err(e) => return Err(e.into()),
         }
 
-        let dir = path_dist.parent().unwrap_or(&path_dist);
-        fs::create_dir_all(dir)?;
-        fs::copy(&path_temp, &path_dist)?;
+        self.publish(&path_temp, &path_rel)?;
 
         self.store_paths.push(path_rel);
         Ok(path_root)
     }
 
+    /// Makes an artifact kept in the cache directory available in `dist` at
+    /// the dist-relative path `rel`. Does nothing while staging.
+    pub(crate) fn publish(&self, cached: &Utf8Path, rel: &Utf8Path) -> std::io::Result<()> {
+        if self.staging {
+            return Ok(());
+        }
+
+        let path_dist = self.out_dir.join(rel);
+        if path_dist.exists() {
+            return Ok(());
+        }
+
+        if let Some(dir) = path_dist.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // hard link with fallback to copy
+        if fs::hard_link(cached, &path_dist).is_err() {
+            fs::copy(cached, &path_dist)?;
+        }
+        Ok(())
+    }
+
     /// Registers a new entry in the global Import Map.
     ///
     /// This tells the browser how to resolve a specific module specifier.
@@ -343,6 +406,7 @@ pub struct FileMetadata {
 #[allow(clippy::unwrap_used)]
 mod test {
     use super::*;
+    use crate::test_utils::TempDir;
 
     #[test]
     fn test_importmap() {
@@ -397,4 +461,23 @@ mod test {
         let _ = std::fs::remove_dir_all(root);
         Ok(())
     }
+
+    #[test]
+    fn test_store_save_staging() -> Result<(), Box<dyn std::error::Error>> {
+        let root = TempDir::new("staging-test");
+
+        let out_dir = root.join("public");
+        let cache_dir = root.join("cache");
+        let mut store = Store::with_dirs(out_dir.clone(), cache_dir.clone());
+        store.staging = true;
+
+        let public_path = store.save(b"asset", "txt")?;
+
+        assert!(!out_dir.exists());
+        assert_eq!(
+            store.store_paths,
+            [public_path.as_str().trim_start_matches('/')]
+        );
+        Ok(())
+    }
 }
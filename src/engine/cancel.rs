@@ -0,0 +1,241 @@
+//! Cooperative cancellation of running tasks.
+//!
+//! Every task receives a [`CancelToken`] through its
+//! [`TaskContext`](crate::TaskContext). The token is cancelled when the
+//! task exceeds its timeout, or when the whole build is abandoned, e.g.
+//! because a file changed while watching. Tasks are never interrupted
+//! forcefully, they are expected to check the token between units of work,
+//! and to wait for child processes with [`CancelToken::wait`].
+
+use std::cell::RefCell;
+use std::io::Read;
+use std::process::{Child, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+/// How often a child process is polled while waiting for it to exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancelToken>> = const { RefCell::new(None) };
+}
+
+/// The error returned by [`CancelToken::check`] once a task was cancelled.
+#[derive(Debug, Error)]
+#[error("task was cancelled")]
+pub struct Cancelled;
+
+/// Signals a running task that it should stop as soon as possible.
+///
+/// Clones of a token share the same state, cancelling one cancels all.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    timeout: Option<Duration>,
+    /// Set once the task is [entered](Self::enter), the time spent waiting
+    /// for a thread doesn't count towards the timeout.
+    deadline: Arc<OnceLock<Instant>>,
+}
+
+impl CancelToken {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token for a single task, cancelled together with `self`
+    /// or once `timeout` has elapsed after the task was entered.
+    pub(crate) fn child(&self, timeout: Option<Duration>) -> Self {
+        Self {
+            cancelled: self.cancelled.clone(),
+            timeout,
+            deadline: Arc::default(),
+        }
+    }
+
+    /// Cancels the token and every token derived from it.
+    #[cfg(any(test, feature = "live"))]
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the deadline of this token has passed.
+    pub(crate) fn timed_out(&self) -> bool {
+        self.deadline
+            .get()
+            .is_some_and(|&deadline| Instant::now() >= deadline)
+    }
+
+    /// Returns `true` if the task should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.timed_out()
+    }
+
+    /// Returns an error if the task should stop, meant to be used with `?`
+    /// between units of work.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        match self.is_cancelled() {
+            true => Err(Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    /// Returns the token of the task running on the current thread.
+    ///
+    /// This is useful for code that has no access to the
+    /// [`TaskContext`](crate::TaskContext), it falls back to a token that is
+    /// never cancelled when called outside of a task.
+    pub fn current() -> Self {
+        CURRENT.with(|current| current.borrow().clone().unwrap_or_default())
+    }
+
+    /// Runs `op` with this token set as the [`current`](Self::current) one.
+    /// The timeout starts when the token is entered for the first time.
+    pub(crate) fn enter<R>(&self, op: impl FnOnce() -> R) -> R {
+        // Restores the previous token even if `op` panics.
+        struct Guard(Option<CancelToken>);
+
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+
+        if let Some(timeout) = self.timeout {
+            self.deadline.get_or_init(|| Instant::now() + timeout);
+        }
+
+        let _guard = Guard(CURRENT.with(|current| current.replace(Some(self.clone()))));
+        op()
+    }
+
+    /// Waits for the child process to exit and collects its output, like
+    /// [`Child::wait_with_output`]. The process is killed if the token is
+    /// cancelled in the meantime.
+    pub fn wait(&self, mut child: Child) -> std::io::Result<Output> {
+        // Closes stdin first, so a child reading it until EOF can exit.
+        drop(child.stdin.take());
+
+        // Both pipes are drained on their own threads, otherwise the child
+        // could block on a full pipe and never exit. The threads are not
+        // joined after a kill, grandchildren may still hold the pipes open.
+        let stdout = read_to_end(child.stdout.take());
+        let stderr = read_to_end(child.stderr.take());
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+
+            if self.is_cancelled() {
+                child.kill()?;
+                child.wait()?;
+                return Err(std::io::Error::other(Cancelled));
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        };
+
+        Ok(Output {
+            status,
+            stdout: join(stdout)?,
+            stderr: join(stderr)?,
+        })
+    }
+}
+
+type Reader = std::thread::JoinHandle<std::io::Result<Vec<u8>>>;
+
+fn read_to_end(pipe: Option<impl Read + Send + 'static>) -> Reader {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut buffer)?;
+        }
+        Ok(buffer)
+    })
+}
+
+fn join(reader: Reader) -> std::io::Result<Vec<u8>> {
+    reader
+        .join()
+        .map_err(|_| std::io::Error::other("pipe reader panicked"))?
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_child_follows_parent() {
+        let build = CancelToken::new();
+        let task = build.child(None);
+        assert!(task.check().is_ok());
+
+        build.cancel();
+        assert!(task.is_cancelled());
+        assert!(!task.timed_out());
+    }
+
+    #[test]
+    fn test_deadline() {
+        let task = CancelToken::new().child(Some(Duration::ZERO));
+        assert!(!task.is_cancelled());
+
+        task.enter(|| {});
+        assert!(task.is_cancelled());
+        assert!(task.timed_out());
+    }
+
+    #[test]
+    fn test_current() {
+        let task = CancelToken::new();
+        task.cancel();
+
+        assert!(!CancelToken::current().is_cancelled());
+        assert!(task.enter(|| CancelToken::current().is_cancelled()));
+        assert!(!CancelToken::current().is_cancelled());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_kills_child() {
+        use std::process::{Command, Stdio};
+
+        let child = Command::new("sleep")
+            .arg("10")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let start = Instant::now();
+        let token = CancelToken::new().child(Some(Duration::from_millis(50)));
+        let result = token.enter(|| token.wait(child));
+
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_closes_stdin() {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.as_mut().unwrap().write_all(b"input").unwrap();
+
+        let token = CancelToken::new().child(Some(Duration::from_secs(5)));
+        let output = token.enter(|| token.wait(child)).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"input");
+    }
+}
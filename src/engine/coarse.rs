@@ -3,9 +3,11 @@ use std::sync::Arc;
 
 use petgraph::graph::NodeIndex;
 
-use crate::core::{Dynamic, Store, TaskContext};
+use crate::core::{Dynamic, Hash32, Store, TaskContext};
 use crate::engine::Handle;
 use crate::engine::tracking::{TrackerPtr, TrackerState, Tracking};
+use crate::stat::StatCache;
+use crate::vfs::Vfs;
 
 /// A "coarse" type-safe reference to a task in the build graph.
 ///
@@ -113,6 +115,14 @@ pub(crate) trait TypedCoarse<G: Send + Sync = ()>: Send + Sync {
     fn requirements(&self) -> Vec<crate::preflight::Requirement> {
         vec![]
     }
+
+    /// Fingerprint of the files read by this task, outside of its dependencies.
+    ///
+    /// Used to validate persisted outputs on a cold start. `None` means the
+    /// output is fully determined by the task's dependencies.
+    fn fingerprint(&self, _: &dyn Vfs, _: &StatCache) -> anyhow::Result<Option<Hash32>> {
+        Ok(None)
+    }
 }
 
 pub(crate) trait Coarse<G: Send + Sync = ()>: Send + Sync {
@@ -146,6 +156,8 @@ pub(crate) trait Coarse<G: Send + Sync = ()>: Send + Sync {
     ) -> bool;
 
     fn requirements(&self) -> Vec<crate::preflight::Requirement>;
+
+    fn fingerprint(&self, vfs: &dyn Vfs, stats: &StatCache) -> anyhow::Result<Option<Hash32>>;
 }
 
 impl<G, T> Coarse<G> for T
@@ -203,4 +215,8 @@ where
     fn requirements(&self) -> Vec<crate::preflight::Requirement> {
         T::requirements(self)
     }
+
+    fn fingerprint(&self, vfs: &dyn Vfs, stats: &StatCache) -> anyhow::Result<Option<Hash32>> {
+        T::fingerprint(self, vfs, stats)
+    }
 }
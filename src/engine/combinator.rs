@@ -0,0 +1,702 @@
+//! Collection combinators over [`Many`] handles.
+//!
+//! These nodes reshape a collection without going through `merge` and
+//! [`Tracker::iter`](crate::Tracker::iter), which would make the consumer
+//! depend on every item. Each output item carries a provenance derived only
+//! from the inputs it was built from, so downstream tasks reading it through a
+//! [`Tracker`](crate::Tracker) are invalidated precisely.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use petgraph::graph::NodeIndex;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{ArcStr, Blake3Hasher, Dynamic, Store, TaskContext};
+use crate::engine::{Many, Map, Provenance, TrackerState, Tracking, TypedFine};
+
+/// Width of the zero-padded position used as the key of sorted collections
+/// and pages, wide enough that keys sort the same way as the positions they
+/// encode.
+const POSITION_WIDTH: usize = 8;
+
+fn provenance_of(value: &impl Hash) -> Provenance {
+    let mut hasher = Blake3Hasher::default();
+    value.hash(&mut hasher);
+    Provenance(hasher.into())
+}
+
+#[allow(clippy::expect_used)] // type invariant enforced by the task graph builder
+fn downcast_map<T: 'static>(output: &Dynamic) -> &Map<T> {
+    output
+        .downcast_ref::<Map<T>>()
+        .expect("Type mismatch in dependency resolution")
+}
+
+/// Keep the items matching a predicate
+/// Many<T> -> Many<T>
+///
+/// Items keep their key and provenance, editing an item that is filtered out
+/// doesn't affect anything downstream.
+pub(crate) struct NodeFilter<G, T, F>
+where
+    G: Send + Sync,
+    T: Send + Sync + Clone + 'static,
+    F: Fn(&T) -> bool + Send + Sync,
+{
+    pub name: Cow<'static, str>,
+    pub dependency: Many<T>,
+    pub predicate: F,
+    pub _phantom: PhantomData<G>,
+}
+
+impl<G, T, F> TypedFine<G> for NodeFilter<G, T, F>
+where
+    G: Send + Sync + 'static,
+    T: Send + Sync + Clone + 'static,
+    F: Fn(&T) -> bool + Send + Sync,
+{
+    type Output = T;
+
+    fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn dependencies(&self) -> Vec<NodeIndex> {
+        vec![self.dependency.index]
+    }
+
+    fn get_watched(&self) -> Vec<camino::Utf8PathBuf> {
+        vec![]
+    }
+
+    fn execute(
+        &self,
+        _: &TaskContext<G>,
+        _: &mut Store,
+        dependencies: &[Dynamic],
+        _: Option<&Dynamic>,
+        _: &HashSet<NodeIndex>,
+    ) -> anyhow::Result<(Tracking, Map<Self::Output>)> {
+        let input = downcast_map::<T>(&dependencies[0]);
+
+        let map = input
+            .map
+            .iter()
+            .filter(|(_, (item, _))| (self.predicate)(item))
+            .map(|(key, (item, provenance))| (key.clone(), (item.clone(), *provenance)))
+            .collect();
+
+        Ok((Tracking::default(), Map { map, dirty: false }))
+    }
+
+    fn is_valid(
+        &self,
+        _: &[Option<TrackerState>],
+        _: &[Dynamic],
+        updated_nodes: &HashSet<NodeIndex>,
+    ) -> bool {
+        !updated_nodes.contains(&self.dependency.index)
+    }
+}
+
+/// Order the items by a key
+/// Many<T> -> Many<T>
+///
+/// The output is keyed by the zero-padded position of each item, so iterating
+/// over it yields the items in sorted order. The provenance of a position
+/// changes only when a different item moves into it or the item itself
+/// changes.
+pub(crate) struct NodeSort<G, T, K, F>
+where
+    G: Send + Sync,
+    T: Send + Sync + Clone + 'static,
+    K: Ord,
+    F: Fn(&T) -> K + Send + Sync,
+{
+    pub name: Cow<'static, str>,
+    pub dependency: Many<T>,
+    pub key: F,
+    pub _phantom: PhantomData<G>,
+}
+
+impl<G, T, K, F> TypedFine<G> for NodeSort<G, T, K, F>
+where
+    G: Send + Sync + 'static,
+    T: Send + Sync + Clone + 'static,
+    K: Ord,
+    F: Fn(&T) -> K + Send + Sync,
+{
+    type Output = T;
+
+    fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn dependencies(&self) -> Vec<NodeIndex> {
+        vec![self.dependency.index]
+    }
+
+    fn get_watched(&self) -> Vec<camino::Utf8PathBuf> {
+        vec![]
+    }
+
+    fn execute(
+        &self,
+        _: &TaskContext<G>,
+        _: &mut Store,
+        dependencies: &[Dynamic],
+        _: Option<&Dynamic>,
+        _: &HashSet<NodeIndex>,
+    ) -> anyhow::Result<(Tracking, Map<Self::Output>)> {
+        let input = downcast_map::<T>(&dependencies[0]);
+
+        // stable sort, items with equal keys stay in key order
+        let mut items: Vec<_> = input.map.iter().collect();
+        items.sort_by_cached_key(|(_, (item, _))| (self.key)(item));
+
+        let map = items
+            .into_iter()
+            .enumerate()
+            .map(|(position, (key, (item, provenance)))| {
+                let position = format!("{position:0POSITION_WIDTH$}");
+                let provenance = provenance_of(&(key, provenance));
+                (ArcStr::from(position), (item.clone(), provenance))
+            })
+            .collect();
+
+        Ok((Tracking::default(), Map { map, dirty: false }))
+    }
+
+    fn is_valid(
+        &self,
+        _: &[Option<TrackerState>],
+        _: &[Dynamic],
+        updated_nodes: &HashSet<NodeIndex>,
+    ) -> bool {
+        !updated_nodes.contains(&self.dependency.index)
+    }
+}
+
+/// Groups an item belongs to, along with the value stored in each of them.
+type Projection<R> = Vec<(String, R)>;
+
+/// Collect items into groups, an item can belong to any number of groups
+/// Many<T> -> Many<Vec<R>>
+///
+/// The callback projects each item to the groups it belongs to, along with
+/// the value stored in each group. The provenance of a group is the hash of
+/// its values, so editing an item only affects the groups whose values
+/// actually changed.
+pub(crate) struct NodeGroup<G, T, R, F>
+where
+    G: Send + Sync,
+    T: Send + Sync + 'static,
+    R: Send + Sync + Hash + Clone + 'static,
+    F: Fn(&T) -> Vec<(String, R)> + Send + Sync,
+{
+    pub name: Cow<'static, str>,
+    pub dependency: Many<T>,
+    pub callback: F,
+    /// Projections from the previous execution, reused for items whose
+    /// provenance did not change.
+    pub previous: Mutex<HashMap<ArcStr, (Provenance, Projection<R>)>>,
+    pub _phantom: PhantomData<G>,
+}
+
+impl<G, T, R, F> TypedFine<G> for NodeGroup<G, T, R, F>
+where
+    G: Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    R: Send + Sync + Hash + Clone + 'static,
+    F: Fn(&T) -> Vec<(String, R)> + Send + Sync,
+{
+    type Output = Vec<R>;
+
+    fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn dependencies(&self) -> Vec<NodeIndex> {
+        vec![self.dependency.index]
+    }
+
+    fn get_watched(&self) -> Vec<camino::Utf8PathBuf> {
+        vec![]
+    }
+
+    fn execute(
+        &self,
+        _: &TaskContext<G>,
+        _: &mut Store,
+        dependencies: &[Dynamic],
+        _: Option<&Dynamic>,
+        _: &HashSet<NodeIndex>,
+    ) -> anyhow::Result<(Tracking, Map<Self::Output>)> {
+        let input = downcast_map::<T>(&dependencies[0]);
+
+        #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+        let mut previous = self.previous.lock().unwrap();
+
+        let mut projected = HashMap::with_capacity(input.map.len());
+        let mut groups = BTreeMap::<ArcStr, Vec<R>>::new();
+
+        for (key, (item, provenance)) in &input.map {
+            let entries = match previous.remove(key) {
+                Some((old_provenance, entries)) if old_provenance == *provenance => entries,
+                _ => (self.callback)(item),
+            };
+
+            for (group, value) in &entries {
+                groups
+                    .entry(group.as_str().into())
+                    .or_default()
+                    .push(value.clone());
+            }
+
+            projected.insert(key.clone(), (*provenance, entries));
+        }
+
+        *previous = projected;
+
+        let map = groups
+            .into_iter()
+            .map(|(group, values)| {
+                let provenance = provenance_of(&values);
+                (group, (values, provenance))
+            })
+            .collect();
+
+        Ok((Tracking::default(), Map { map, dirty: false }))
+    }
+
+    fn is_valid(
+        &self,
+        _: &[Option<TrackerState>],
+        _: &[Dynamic],
+        updated_nodes: &HashSet<NodeIndex>,
+    ) -> bool {
+        !updated_nodes.contains(&self.dependency.index)
+    }
+}
+
+/// Pair each item with the item of another collection it refers to
+/// Many<T> + Many<U> -> Many<(T, U)>
+///
+/// This is an inner join, items whose key is missing from the other
+/// collection are left out. Each pair keeps the key of the left item and is
+/// only invalidated when either side of the pair changes.
+pub(crate) struct NodeJoin<G, T, U, F>
+where
+    G: Send + Sync,
+    T: Send + Sync + Clone + 'static,
+    U: Send + Sync + Clone + 'static,
+    F: Fn(&T) -> String + Send + Sync,
+{
+    pub name: Cow<'static, str>,
+    pub left: Many<T>,
+    pub right: Many<U>,
+    pub key: F,
+    pub _phantom: PhantomData<G>,
+}
+
+impl<G, T, U, F> TypedFine<G> for NodeJoin<G, T, U, F>
+where
+    G: Send + Sync + 'static,
+    T: Send + Sync + Clone + 'static,
+    U: Send + Sync + Clone + 'static,
+    F: Fn(&T) -> String + Send + Sync,
+{
+    type Output = (T, U);
+
+    fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn dependencies(&self) -> Vec<NodeIndex> {
+        vec![self.left.index, self.right.index]
+    }
+
+    fn get_watched(&self) -> Vec<camino::Utf8PathBuf> {
+        vec![]
+    }
+
+    fn execute(
+        &self,
+        _: &TaskContext<G>,
+        _: &mut Store,
+        dependencies: &[Dynamic],
+        _: Option<&Dynamic>,
+        _: &HashSet<NodeIndex>,
+    ) -> anyhow::Result<(Tracking, Map<Self::Output>)> {
+        let left = downcast_map::<T>(&dependencies[0]);
+        let right = downcast_map::<U>(&dependencies[1]);
+
+        let map = left
+            .map
+            .iter()
+            .filter_map(|(key, (item, provenance))| {
+                let (other, other_provenance) = right.map.get((self.key)(item).as_str())?;
+                let provenance = provenance_of(&(provenance, other_provenance));
+                Some((key.clone(), ((item.clone(), other.clone()), provenance)))
+            })
+            .collect();
+
+        Ok((Tracking::default(), Map { map, dirty: false }))
+    }
+
+    fn is_valid(
+        &self,
+        _: &[Option<TrackerState>],
+        _: &[Dynamic],
+        updated_nodes: &HashSet<NodeIndex>,
+    ) -> bool {
+        !updated_nodes.contains(&self.left.index) && !updated_nodes.contains(&self.right.index)
+    }
+}
+
+/// A single listing page, produced by `.each(items).paginate(...)`.
+///
+/// Page numbers start at 1 and the pages are keyed by their zero-padded
+/// number (`00000001`, `00000002`, ...), so they iterate in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// Number of this page, starting at 1.
+    pub number: usize,
+    /// Items on this page, in sorted order.
+    pub items: Vec<T>,
+    /// Number of the previous page, if any.
+    pub prev: Option<usize>,
+    /// Number of the next page, if any.
+    pub next: Option<usize>,
+}
+
+/// Split sorted items into pages of a fixed size
+/// Many<T> -> Many<Page<T>>
+///
+/// The provenance of a page is derived from the provenances of its items and
+/// its neighbours, so editing an item only invalidates the page holding it,
+/// unless the edit changes the order. The total number of pages is
+/// deliberately not part of a page, otherwise adding a page would invalidate
+/// all of them.
+pub(crate) struct NodePaginate<G, T, K, F>
+where
+    G: Send + Sync,
+    T: Send + Sync + Clone + 'static,
+    K: Ord,
+    F: Fn(&T) -> K + Send + Sync,
+{
+    pub name: Cow<'static, str>,
+    pub dependency: Many<T>,
+    pub per_page: usize,
+    pub key: F,
+    pub _phantom: PhantomData<G>,
+}
+
+impl<G, T, K, F> TypedFine<G> for NodePaginate<G, T, K, F>
+where
+    G: Send + Sync + 'static,
+    T: Send + Sync + Clone + 'static,
+    K: Ord,
+    F: Fn(&T) -> K + Send + Sync,
+{
+    type Output = Page<T>;
+
+    fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn dependencies(&self) -> Vec<NodeIndex> {
+        vec![self.dependency.index]
+    }
+
+    fn get_watched(&self) -> Vec<camino::Utf8PathBuf> {
+        vec![]
+    }
+
+    fn execute(
+        &self,
+        _: &TaskContext<G>,
+        _: &mut Store,
+        dependencies: &[Dynamic],
+        _: Option<&Dynamic>,
+        _: &HashSet<NodeIndex>,
+    ) -> anyhow::Result<(Tracking, Map<Self::Output>)> {
+        if self.per_page == 0 {
+            anyhow::bail!("Pages must hold at least one item, `per_page` is 0");
+        }
+
+        let input = downcast_map::<T>(&dependencies[0]);
+
+        let mut items: Vec<_> = input.map.iter().collect();
+        items.sort_by_cached_key(|(_, (item, _))| (self.key)(item));
+
+        // an empty collection still gets the first page
+        let mut chunks: Vec<_> = items.chunks(self.per_page).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+
+        let total = chunks.len();
+        let map = chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let number = index + 1;
+                let prev = (number > 1).then(|| number - 1);
+                let next = (number < total).then(|| number + 1);
+
+                let sources: Vec<_> = chunk.iter().map(|(key, (_, prov))| (key, prov)).collect();
+                let provenance = provenance_of(&(number, prev, next, sources));
+
+                let page = Page {
+                    number,
+                    items: chunk.iter().map(|(_, (item, _))| item.clone()).collect(),
+                    prev,
+                    next,
+                };
+
+                let key = format!("{number:0POSITION_WIDTH$}");
+                (ArcStr::from(key), (page, provenance))
+            })
+            .collect();
+
+        Ok((Tracking::default(), Map { map, dirty: false }))
+    }
+
+    fn is_valid(
+        &self,
+        _: &[Option<TrackerState>],
+        _: &[Dynamic],
+        updated_nodes: &HashSet<NodeIndex>,
+    ) -> bool {
+        !updated_nodes.contains(&self.dependency.index)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::core::Hash32;
+    use crate::test_utils::make_ctx;
+
+    fn make_map<T>(items: Vec<(&str, T, &str)>) -> Dynamic
+    where
+        T: Send + Sync + 'static,
+    {
+        let map = items
+            .into_iter()
+            .map(|(key, item, version)| (key.into(), (item, Provenance(Hash32::hash(version)))))
+            .collect();
+        Arc::new(Map { map, dirty: false })
+    }
+
+    fn run<N, O>(node: &N, inputs: &[Dynamic]) -> Map<O>
+    where
+        N: TypedFine<(), Output = O>,
+    {
+        let (_, map) = node
+            .execute(
+                &make_ctx(),
+                &mut Store::new(),
+                inputs,
+                None,
+                &HashSet::new(),
+            )
+            .unwrap();
+        map
+    }
+
+    #[derive(Clone)]
+    struct Post {
+        tags: Vec<&'static str>,
+        body: &'static str,
+    }
+
+    fn post(tags: Vec<&'static str>, body: &'static str) -> Post {
+        Post { tags, body }
+    }
+
+    #[test]
+    fn test_filter_keeps_provenance() {
+        let node = NodeFilter {
+            name: "filter".into(),
+            dependency: Many::<i32>::new(NodeIndex::new(0)),
+            predicate: |item: &i32| *item > 1,
+            _phantom: PhantomData::<()>,
+        };
+
+        let input = make_map(vec![("a", 1, "a"), ("b", 2, "b")]);
+        let output = run(&node, &[input]);
+
+        assert_eq!(output.map.len(), 1);
+        assert_eq!(output.map["b"], (2, Provenance(Hash32::hash("b"))));
+    }
+
+    #[test]
+    fn test_sort_positions() {
+        let node = NodeSort {
+            name: "sort".into(),
+            dependency: Many::<i32>::new(NodeIndex::new(0)),
+            key: |item: &i32| std::cmp::Reverse(*item),
+            _phantom: PhantomData::<()>,
+        };
+
+        let input_1 = make_map(vec![("a", 1, "a1"), ("b", 2, "b1"), ("c", 3, "c1")]);
+        let input_2 = make_map(vec![("a", 1, "a2"), ("b", 2, "b1"), ("c", 3, "c1")]);
+        let output_1 = run(&node, &[input_1]);
+        let output_2 = run(&node, &[input_2]);
+
+        let values: Vec<_> = output_1.map.values().map(|(item, _)| *item).collect();
+        assert_eq!(values, [3, 2, 1]);
+        assert!(output_1.map.contains_key("00000000"));
+
+        // only the position holding the edited item changed
+        assert_eq!(output_1.map["00000000"].1, output_2.map["00000000"].1);
+        assert_eq!(output_1.map["00000001"].1, output_2.map["00000001"].1);
+        assert_ne!(output_1.map["00000002"].1, output_2.map["00000002"].1);
+    }
+
+    #[test]
+    fn test_group_ignores_unrelated_edits() {
+        let node = NodeGroup {
+            name: "group".into(),
+            dependency: Many::<Post>::new(NodeIndex::new(0)),
+            callback: |post: &Post| {
+                post.tags
+                    .iter()
+                    .map(|tag| (tag.to_string(), post.tags.len()))
+                    .collect()
+            },
+            previous: Mutex::default(),
+            _phantom: PhantomData::<()>,
+        };
+
+        let input_1 = make_map(vec![
+            ("a", post(vec!["rust"], "hello"), "a1"),
+            ("b", post(vec!["rust", "web"], "world"), "b1"),
+        ]);
+        let input_2 = make_map(vec![
+            ("a", post(vec!["rust"], "edited"), "a2"),
+            ("b", post(vec!["rust", "web"], "world"), "b1"),
+        ]);
+
+        let output_1 = run(&node, &[input_1]);
+        let output_2 = run(&node, &[input_2]);
+
+        assert_eq!(output_1.map["rust"].0, [1, 2]);
+        assert_eq!(output_1.map["web"].0, [2]);
+        assert_eq!(
+            output_1.map["rust"].1, output_2.map["rust"].1,
+            "Group should be unchanged if only the body changed"
+        );
+
+        // the projection is cached by provenance
+        let previous = node.previous.lock().unwrap();
+        assert_eq!(previous["a"].0, Provenance(Hash32::hash("a2")));
+    }
+
+    #[test]
+    fn test_join_pairs_items() {
+        let node = NodeJoin {
+            name: "join".into(),
+            left: Many::<Post>::new(NodeIndex::new(0)),
+            right: Many::<i32>::new(NodeIndex::new(1)),
+            key: |post: &Post| post.tags[0].to_string(),
+            _phantom: PhantomData::<()>,
+        };
+
+        let posts = make_map(vec![
+            ("a", post(vec!["rust"], ""), "a"),
+            ("b", post(vec!["missing"], ""), "b"),
+        ]);
+        let tags = make_map(vec![("rust", 7, "rust")]);
+        let output = run(&node, &[posts, tags]);
+
+        assert_eq!(output.map.len(), 1);
+        let ((post, tag), _) = &output.map["a"];
+        assert_eq!((post.body, *tag), ("", 7));
+    }
+
+    #[test]
+    fn test_paginate_invalidates_single_page() {
+        let node = NodePaginate {
+            name: "paginate".into(),
+            dependency: Many::<i32>::new(NodeIndex::new(0)),
+            per_page: 2,
+            key: |item: &i32| *item,
+            _phantom: PhantomData::<()>,
+        };
+
+        let input_1 = make_map(vec![("a", 1, "a1"), ("b", 2, "b1"), ("c", 3, "c1")]);
+        let input_2 = make_map(vec![("a", 1, "a1"), ("b", 2, "b1"), ("c", 3, "c2")]);
+        let output_1 = run(&node, &[input_1]);
+        let output_2 = run(&node, &[input_2]);
+
+        let (first, _) = &output_1.map["00000001"];
+        assert_eq!(first.items, [1, 2]);
+        assert_eq!((first.prev, first.next), (None, Some(2)));
+        let (last, _) = &output_1.map["00000002"];
+        assert_eq!(last.items, [3]);
+        assert_eq!((last.prev, last.next), (Some(1), None));
+
+        // only the page holding the edited item changed
+        assert_eq!(output_1.map["00000001"].1, output_2.map["00000001"].1);
+        assert_ne!(output_1.map["00000002"].1, output_2.map["00000002"].1);
+
+        let empty = run(&node, &[make_map::<i32>(vec![])]);
+        assert_eq!(empty.map["00000001"].0.items, Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_paginate_orders_many_pages() {
+        let node = NodePaginate {
+            name: "paginate".into(),
+            dependency: Many::<i32>::new(NodeIndex::new(0)),
+            per_page: 1,
+            key: |item: &i32| *item,
+            _phantom: PhantomData::<()>,
+        };
+
+        let items: Vec<_> = (1..=12).map(|n| (n.to_string(), n)).collect();
+        let input = make_map(
+            items
+                .iter()
+                .map(|(k, n)| (k.as_str(), *n, k.as_str()))
+                .collect(),
+        );
+        let output = run(&node, &[input]);
+
+        let numbers: Vec<_> = output.map.values().map(|(page, _)| page.number).collect();
+        assert_eq!(numbers, (1..=12).collect::<Vec<_>>());
+        assert_eq!(output.map["00000010"].0.items, [10]);
+    }
+
+    #[test]
+    fn test_paginate_rejects_empty_pages() {
+        let node = NodePaginate {
+            name: "paginate".into(),
+            dependency: Many::<i32>::new(NodeIndex::new(0)),
+            per_page: 0,
+            key: |item: &i32| *item,
+            _phantom: PhantomData::<()>,
+        };
+
+        let input = make_map(vec![("a", 1, "a")]);
+        let result = node.execute(
+            &make_ctx(),
+            &mut Store::new(),
+            &[input],
+            None,
+            &HashSet::new(),
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! Early cutoff for tasks that produce the same output as before.
+//!
+//! Normally, every task that is re-executed counts as updated, which in turn
+//! invalidates every task holding a [`One`] to it. Tasks registered with
+//! [`Blueprint::cutoff`](crate::Blueprint::cutoff) have their output hashed
+//! after each execution instead, and they are only considered updated when
+//! the hash differs from the previous one.
+
+use std::hash::Hash;
+
+use crate::core::{Blake3Hasher, Dynamic, Hash32};
+use crate::engine::{Handle, Many, Map, One};
+
+/// Hashes the type-erased output of a single task.
+#[derive(Clone, Copy)]
+pub struct OutputHasher(fn(&Dynamic) -> Option<Hash32>);
+
+impl OutputHasher {
+    pub(crate) fn hash(&self, output: &Dynamic) -> Option<Hash32> {
+        (self.0)(output)
+    }
+}
+
+/// Handles whose output can be compared between executions.
+pub trait Cutoff: Handle {
+    fn hasher(&self) -> OutputHasher;
+}
+
+impl<T> Cutoff for One<T>
+where
+    T: Hash + Send + Sync + 'static,
+{
+    fn hasher(&self) -> OutputHasher {
+        OutputHasher(hash_one::<T>)
+    }
+}
+
+impl<T> Cutoff for Many<T>
+where
+    T: Hash + Send + Sync + 'static,
+{
+    fn hasher(&self) -> OutputHasher {
+        OutputHasher(hash_many::<T>)
+    }
+}
+
+fn hash_one<T: Hash + 'static>(output: &Dynamic) -> Option<Hash32> {
+    let output = output.downcast_ref::<T>()?;
+    let mut hasher = Blake3Hasher::default();
+    output.hash(&mut hasher);
+    Some(hasher.into())
+}
+
+fn hash_many<T: Hash + 'static>(output: &Dynamic) -> Option<Hash32> {
+    let output = output.downcast_ref::<Map<T>>()?;
+    let mut hasher = Blake3Hasher::default();
+    output.map.hash(&mut hasher);
+    Some(hasher.into())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use petgraph::graph::NodeIndex;
+
+    use crate::engine::Provenance;
+
+    #[test]
+    fn test_one_hasher_compares_content() {
+        let hasher = One::<String>::new(NodeIndex::new(0)).hasher();
+        let a: Dynamic = Arc::new(String::from("page"));
+        let b: Dynamic = Arc::new(String::from("page"));
+        let c: Dynamic = Arc::new(String::from("other"));
+
+        assert_eq!(hasher.hash(&a), hasher.hash(&b));
+        assert_ne!(hasher.hash(&a), hasher.hash(&c));
+        assert_eq!(hasher.hash(&(Arc::new(1u32) as Dynamic)), None);
+    }
+
+    #[test]
+    fn test_many_hasher_ignores_dirty_flag() {
+        let hasher = Many::<u32>::new(NodeIndex::new(0)).hasher();
+        let mut map = BTreeMap::new();
+        map.insert("a".into(), (1, Provenance(Hash32::hash(b"a"))));
+
+        let a: Dynamic = Arc::new(Map {
+            map: map.clone(),
+            dirty: false,
+        });
+        let b: Dynamic = Arc::new(Map { map, dirty: true });
+
+        assert_eq!(hasher.hash(&a), hasher.hash(&b));
+    }
+}
@@ -4,9 +4,11 @@ use std::sync::Arc;
 use glob::Pattern;
 use petgraph::graph::NodeIndex;
 
-use crate::core::{Dynamic, Store, TaskContext};
+use crate::core::{Dynamic, Hash32, Store, TaskContext};
 use crate::engine::Map;
 use crate::engine::tracking::{Tracker, TrackerPtr, TrackerState, Tracking};
+use crate::stat::StatCache;
+use crate::vfs::Vfs;
 
 /// A "fine" type-safe reference to a task in the build graph.
 ///
@@ -227,6 +229,14 @@ pub(crate) trait TypedFine<G: Send + Sync = ()>: Send + Sync {
     fn requirements(&self) -> Vec<crate::preflight::Requirement> {
         vec![]
     }
+
+    /// Fingerprint of the files read by this task, outside of its dependencies.
+    ///
+    /// Used to validate persisted outputs on a cold start. `None` means the
+    /// output is fully determined by the task's dependencies.
+    fn fingerprint(&self, _: &dyn Vfs, _: &StatCache) -> anyhow::Result<Option<Hash32>> {
+        Ok(None)
+    }
 }
 
 /// The core trait for all tasks in the graph.
@@ -267,6 +277,8 @@ pub(crate) trait Fine<G: Send + Sync = ()>: Send + Sync {
     ) -> bool;
 
     fn requirements(&self) -> Vec<crate::preflight::Requirement>;
+
+    fn fingerprint(&self, vfs: &dyn Vfs, stats: &StatCache) -> anyhow::Result<Option<Hash32>>;
 }
 
 // A blanket implementation to automatically bridge the two. This is where the
@@ -336,6 +348,10 @@ where
     fn requirements(&self) -> Vec<crate::preflight::Requirement> {
         T::requirements(self)
     }
+
+    fn fingerprint(&self, vfs: &dyn Vfs, stats: &StatCache) -> anyhow::Result<Option<Hash32>> {
+        T::fingerprint(self, vfs, stats)
+    }
 }
 
 #[cfg(test)]
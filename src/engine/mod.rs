@@ -1,6 +1,11 @@
+mod cancel;
 mod coarse;
+mod combinator;
+mod cutoff;
 mod fine;
 mod node;
+mod persist;
+mod resource;
 mod runner;
 mod tracking;
 
@@ -8,23 +13,39 @@ use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 
 use petgraph::graph::NodeIndex;
+use serde::{Deserialize, Serialize};
 
 use crate::core::{ArcStr, Dynamic, Hash32};
+use crate::stat::StatCache;
+use crate::vfs::Vfs;
 
 pub(crate) use coarse::TypedCoarse;
+pub(crate) use combinator::{NodeFilter, NodeGroup, NodeJoin, NodePaginate, NodeSort};
+pub(crate) use cutoff::{Cutoff, OutputHasher};
 pub(crate) use fine::TypedFine;
-pub(crate) use node::{NodeGather, NodeMap, NodeScatter};
+pub(crate) use node::{NodeGather, NodeMap, NodeScatter, NodeScatterKeyed};
+pub(crate) use persist::{Codec, Persistent, cached_store_path};
+pub(crate) use resource::{Limit, ResourceClass};
+#[cfg(any(test, feature = "testing"))]
+pub(crate) use runner::NodeData;
 #[cfg(feature = "live")]
 pub(crate) use runner::watch;
-pub(crate) use runner::{TaskExecution, collect_manifest, run_once_parallel, run_tasks_parallel};
-pub(crate) use tracking::{TrackerPtr, TrackerState, Tracking};
-
+pub(crate) use runner::{
+    TaskExecution, Timings, collect_manifest, find_required, run_once_parallel,
+    run_subgraph_parallel, run_tasks_parallel,
+};
+#[cfg(any(test, feature = "live", feature = "testing"))]
+pub(crate) use runner::{find_affected, find_dirty};
+pub(crate) use tracking::TrackerPtr;
+
+pub use cancel::{CancelToken, Cancelled};
 pub use coarse::One;
+pub use combinator::Page;
 pub use fine::Many;
 pub use runner::Diagnostics;
-pub use tracking::Tracker;
+pub use tracking::{Tracker, TrackerState, Tracking};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Provenance(pub(crate) Hash32);
 
 /// A collection of processed assets, indexed by unique identifier.
@@ -118,6 +139,17 @@ where
             Task::F(task) => task.requirements(),
         }
     }
+
+    pub(crate) fn fingerprint(
+        &self,
+        vfs: &dyn Vfs,
+        stats: &StatCache,
+    ) -> anyhow::Result<Option<Hash32>> {
+        match self {
+            Task::C(task) => task.fingerprint(vfs, stats),
+            Task::F(task) => task.fingerprint(vfs, stats),
+        }
+    }
 }
 
 impl<G> Clone for Task<G> {
@@ -136,6 +168,27 @@ impl<G> Clone for Task<G> {
 /// passed as the `dependencies` argument to `Blueprint::add_task`. It provides
 /// the necessary logic for the build system to extract dependency information
 /// and resolve their outputs.
+///
+/// With the `macros` feature it can also be derived for structs whose fields
+/// implement `Dependencies`. The resolved output is a generated `{Name}View`
+/// struct with the same field names, tracking works the same as for tuples.
+///
+/// ```rust,no_run
+/// use hauchiwa::{Dependencies, Many, One};
+///
+/// #[derive(Dependencies)]
+/// struct Deps {
+///     posts: Many<String>,
+///     title: One<String>,
+/// }
+///
+/// # let mut config = hauchiwa::Blueprint::<()>::new();
+/// # let posts = config.task().using(()).spread(|_, _| Ok(vec![("a".to_string(), String::new())]));
+/// # let title = config.task().run(|_| Ok(String::from("Blog")));
+/// config.task().using(Deps { posts, title }).merge(|_, deps: DepsView| {
+///     Ok(format!("{}: {} posts", deps.title, deps.posts.values().count()))
+/// });
+/// ```
 pub trait Dependencies {
     /// The resulting type when all dependencies are resolved.
     /// For a tuple of [`Handle<T>`]s, this will be a tuple of `&'a T`s.
@@ -1,12 +1,13 @@
 use std::borrow::Cow;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::sync::Mutex;
 
 use petgraph::graph::NodeIndex;
 
 use crate::Many;
-use crate::core::{Blake3Hasher, Dynamic, Store, TaskContext};
+use crate::core::{ArcStr, Blake3Hasher, Dynamic, Store, TaskContext};
 use crate::engine::{
     Dependencies, Map, Provenance, TrackerState, Tracking, TypedCoarse, TypedFine,
 };
@@ -153,6 +154,134 @@ where
     }
 }
 
+/// Explode dependencies into multiple outputs, one callback call per key
+/// Dependencies -> Many<R>
+///
+/// Every item is produced with its own dependency tracking, so that on
+/// re-execution only the keys whose accessed inputs changed are regenerated,
+/// the rest is taken from the previous output.
+///
+/// Constraints:
+/// - R must be Hash, because it will be used for tracking
+/// - R must be Clone, because unchanged items are reused
+pub(crate) struct NodeScatterKeyed<G, R, D, K, F>
+where
+    G: Send + Sync,
+    R: Send + Sync + Hash + Clone + 'static,
+    D: Dependencies,
+    K: for<'a> Fn(&TaskContext<'a, G>, D::Output<'a>) -> anyhow::Result<Vec<String>> + Send + Sync,
+    F: for<'a> Fn(&TaskContext<'a, G>, &str, D::Output<'a>) -> anyhow::Result<R> + Send + Sync,
+{
+    pub name: Cow<'static, str>,
+    pub dependencies: D,
+    pub keys: K,
+    pub callback: F,
+    /// Tracking recorded for each key during the previous execution.
+    pub previous: Mutex<HashMap<ArcStr, Vec<Option<TrackerState>>>>,
+    pub _phantom: PhantomData<G>,
+}
+
+impl<G, R, D, K, F> TypedFine<G> for NodeScatterKeyed<G, R, D, K, F>
+where
+    G: Send + Sync + 'static,
+    R: Send + Sync + Hash + Clone + 'static,
+    D: Dependencies + Send + Sync,
+    K: for<'a> Fn(&TaskContext<'a, G>, D::Output<'a>) -> anyhow::Result<Vec<String>>
+        + Send
+        + Sync
+        + 'static,
+    F: for<'a> Fn(&TaskContext<'a, G>, &str, D::Output<'a>) -> anyhow::Result<R>
+        + Send
+        + Sync
+        + 'static,
+{
+    type Output = R;
+
+    fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn dependencies(&self) -> Vec<NodeIndex> {
+        self.dependencies.dependencies()
+    }
+
+    fn get_watched(&self) -> Vec<camino::Utf8PathBuf> {
+        vec![]
+    }
+
+    fn execute(
+        &self,
+        context: &TaskContext<G>,
+        _: &mut Store,
+        dependencies: &[Dynamic],
+        old_output: Option<&Dynamic>,
+        updated_nodes: &HashSet<NodeIndex>,
+    ) -> anyhow::Result<(Tracking, Map<Self::Output>)> {
+        let (tracking, inputs) = self.dependencies.resolve(dependencies);
+        let keys = (self.keys)(context, inputs)?;
+
+        // The task as a whole depends on everything read while listing the
+        // keys, and on everything read for any of the keys.
+        let mut merged = tracking.unwrap();
+
+        let old_map = old_output.and_then(|d| d.downcast_ref::<Map<Self::Output>>());
+
+        #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+        let mut previous = self.previous.lock().unwrap();
+
+        let mut tracked = HashMap::new();
+        let mut map = BTreeMap::new();
+
+        for key in keys {
+            let key = ArcStr::from(key);
+
+            let (item, provenance, key_tracking) = if let Some(old_map) = old_map
+                && let Some((old_item, old_provenance)) = old_map.map.get(&key)
+                && let Some(old_tracking) = previous.remove(&key)
+                && self
+                    .dependencies
+                    .is_valid(&old_tracking, dependencies, updated_nodes)
+            {
+                (old_item.clone(), *old_provenance, old_tracking)
+            } else {
+                let (tracking, inputs) = self.dependencies.resolve(dependencies);
+                let item = (self.callback)(context, &key, inputs)?;
+
+                let hash = {
+                    let mut hasher = Blake3Hasher::default();
+                    item.hash(&mut hasher);
+                    hasher.into()
+                };
+
+                (item, Provenance(hash), tracking.unwrap())
+            };
+
+            for (merged, state) in merged.iter_mut().zip(&key_tracking) {
+                if let (Some(merged), Some(state)) = (merged, state) {
+                    merged.merge(state);
+                }
+            }
+
+            tracked.insert(key.clone(), key_tracking);
+            map.insert(key, (item, provenance));
+        }
+
+        *previous = tracked;
+
+        Ok((Tracking::from_states(merged), Map { map, dirty: false }))
+    }
+
+    fn is_valid(
+        &self,
+        old_tracking: &[Option<TrackerState>],
+        new_outputs: &[Dynamic],
+        updated_nodes: &HashSet<NodeIndex>,
+    ) -> bool {
+        self.dependencies
+            .is_valid(old_tracking, new_outputs, updated_nodes)
+    }
+}
+
 /// Map each input to a single output, with additional (side) dependencies
 /// Many<T> -> Many<R>
 pub(crate) struct NodeMap<T, G, R, D, F>
@@ -272,36 +401,21 @@ where
 mod tests {
     use super::*;
 
-    use crate::Environment;
-    use crate::core::{Dynamic, Hash32, ImportMap, Store, TaskContext};
+    use crate::core::{Dynamic, Hash32, Store};
     use crate::engine::{
         Many, Map, One, Provenance, TrackerState, Tracking, TypedCoarse, TypedFine,
     };
+    use crate::test_utils::make_ctx;
 
     use std::collections::HashSet;
     use std::marker::PhantomData;
     use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     use petgraph::graph::NodeIndex;
 
-    const ENV: Environment = Environment {
-        generator: "test",
-        mode: crate::core::Mode::Build,
-        port: None,
-        data: (),
-    };
-
     // --- Helpers ---
 
-    fn make_ctx() -> TaskContext<'static, ()> {
-        TaskContext {
-            env: &ENV,
-            importmap: Box::leak(Box::new(ImportMap::new())),
-            span: tracing::Span::none(),
-            progress: Box::leak(Box::new(crate::utils::ProgressStyles::default())),
-        }
-    }
-
     fn make_coarse_output(val: i32) -> Dynamic {
         Arc::new(val)
     }
@@ -522,6 +636,117 @@ mod tests {
         Ok(())
     }
 
+    // --- NodeScatterKeyed Tests ---
+
+    #[test]
+    fn test_scatter_keyed_reuse() -> anyhow::Result<()> {
+        let dep_ref = NodeIndex::new(1);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let node = NodeScatterKeyed {
+            name: "scatter_keyed".into(),
+            dependencies: Many::<i32>::new(dep_ref),
+            keys: |_, tracker| Ok(tracker.iter().map(|(key, _)| key.to_string()).collect()),
+            callback: move |_, key, tracker| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                Ok(*tracker.get(key)? * 10)
+            },
+            previous: Default::default(),
+            _phantom: PhantomData::<()>,
+        };
+
+        let updated = HashSet::from_iter([dep_ref]);
+
+        let input_1 = map! { "a" => 1, 1; "b" => 2, 1 };
+        let input_2: Dynamic = map! { "a" => 1, 1; "b" => 3, 2 }; // only "b" changed
+
+        let (tracking, out_1) = node.execute(
+            &make_ctx(),
+            &mut Store::new(),
+            &[input_1],
+            None,
+            &HashSet::new(),
+        )?;
+        let state = extract_state(tracking).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        // The task as a whole depends on every key
+        let input_2 = std::slice::from_ref(&input_2);
+        let is_not_valid = !node.is_valid(&[Some(state)], input_2, &updated);
+        assert!(is_not_valid, "Should be invalid if any read key changed");
+
+        let old_dynamic: Dynamic = Arc::new(out_1);
+        let (_, out_2) = node.execute(
+            &make_ctx(),
+            &mut Store::new(),
+            input_2,
+            Some(&old_dynamic),
+            &updated,
+        )?;
+
+        // Only "b" was regenerated
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        assert_eq!(out_2.map.get("a").unwrap().0, 10);
+        assert_eq!(out_2.map.get("b").unwrap().0, 30);
+
+        Ok(())
+    }
+
+    // --- Derived Dependencies Tests ---
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn test_derived_dependencies_offsets() {
+        use crate::Dependencies;
+
+        #[derive(Dependencies)]
+        struct Deps {
+            title: One<i32>,
+            pages: Vec<Many<i32>>,
+            posts: Many<i32>,
+        }
+
+        let deps = Deps {
+            title: One::new(NodeIndex::new(1)),
+            pages: vec![Many::new(NodeIndex::new(2)), Many::new(NodeIndex::new(3))],
+            posts: Many::new(NodeIndex::new(4)),
+        };
+
+        let indices: Vec<_> = deps.dependencies().iter().map(|i| i.index()).collect();
+        assert_eq!(indices, [1, 2, 3, 4]);
+
+        let inputs = [
+            make_coarse_output(1),
+            map! { "a" => 2, 1 },
+            map! { "a" => 3, 1 },
+            map! { "a" => 4, 1; "b" => 5, 1 },
+        ];
+
+        let (tracking, view) = deps.resolve(&inputs);
+        assert_eq!(*view.title, 1);
+        assert_eq!(*view.pages[1].get("a").unwrap(), 3);
+        assert_eq!(*view.posts.get("b").unwrap(), 5);
+        drop(view);
+
+        let state = tracking.unwrap();
+        assert!(state[0].is_none());
+        assert!(state[1].as_ref().unwrap().accessed.is_empty());
+        assert!(state[3].as_ref().unwrap().accessed.contains_key("b"));
+
+        // Only "a" of posts changed, which was never read
+        let updated = HashSet::from_iter([NodeIndex::new(4)]);
+        let changed = [
+            make_coarse_output(1),
+            map! { "a" => 2, 1 },
+            map! { "a" => 3, 1 },
+            map! { "a" => 9, 2; "b" => 5, 1 },
+        ];
+        assert!(deps.is_valid(&state, &changed, &updated));
+
+        let updated = HashSet::from_iter([NodeIndex::new(1)]);
+        assert!(!deps.is_valid(&state, &changed, &updated));
+    }
+
     // --- NodeMap Tests ---
 
     #[test]
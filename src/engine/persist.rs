@@ -0,0 +1,501 @@
+//! Persistent on-disk cache of task outputs.
+//!
+//! Tasks registered with [`Blueprint::persist`](crate::Blueprint::persist) have
+//! their output, dependency tracking and import map written to
+//! `{cache_dir}/tasks/` after a successful build. On the next cold start these
+//! entries are loaded back into the node cache, which lets the scheduler skip
+//! every task whose inputs did not change.
+//!
+//! Entries are keyed by the name and output type of their task, so adding or
+//! removing other tasks doesn't invalidate them. Entries of tasks which are
+//! no longer persisted are removed when the cache is saved.
+//!
+//! An entry is only restored when:
+//! 1. It was written by the same executable, so changed task code is never
+//!    mixed with stale outputs.
+//! 2. The task still has the same name and output type.
+//! 3. The fingerprint of the files read by the task still matches.
+//! 4. Every content-addressed asset produced by the task is still available,
+//!    either in `dist` or in the cache directory.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use petgraph::graph::NodeIndex;
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::Website;
+use crate::core::{ArcStr, Dynamic, Hash32, ImportMap};
+use crate::engine::runner::NodeData;
+use crate::engine::{Handle, Many, Map, One, Provenance, TrackerState};
+
+const DIR_TASKS: &str = "tasks";
+
+/// Serializes and deserializes the type-erased output of a single task.
+pub trait Codec: Send + Sync {
+    fn encode(&self, output: &Dynamic, writer: &mut dyn Write) -> anyhow::Result<()>;
+    fn decode(&self, reader: &mut dyn Read) -> anyhow::Result<Dynamic>;
+}
+
+/// Handles whose output can be stored in the persistent task cache.
+pub trait Persistent: Handle {
+    fn codec(&self) -> Arc<dyn Codec>;
+}
+
+struct OneCodec<T>(PhantomData<fn() -> T>);
+
+impl<T> Codec for OneCodec<T>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn encode(&self, output: &Dynamic, writer: &mut dyn Write) -> anyhow::Result<()> {
+        let output = output
+            .downcast_ref::<T>()
+            .ok_or_else(|| anyhow::anyhow!("Type mismatch in persistent cache"))?;
+        ciborium::into_writer(output, writer)?;
+        Ok(())
+    }
+
+    fn decode(&self, reader: &mut dyn Read) -> anyhow::Result<Dynamic> {
+        Ok(Arc::new(ciborium::from_reader::<T, _>(reader)?))
+    }
+}
+
+impl<T> Persistent for One<T>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn codec(&self) -> Arc<dyn Codec> {
+        Arc::new(OneCodec::<T>(PhantomData))
+    }
+}
+
+struct ManyCodec<T>(PhantomData<fn() -> T>);
+
+impl<T> Codec for ManyCodec<T>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn encode(&self, output: &Dynamic, writer: &mut dyn Write) -> anyhow::Result<()> {
+        let output = output
+            .downcast_ref::<Map<T>>()
+            .ok_or_else(|| anyhow::anyhow!("Type mismatch in persistent cache"))?;
+        let items: Vec<_> = output
+            .map
+            .iter()
+            .map(|(key, (item, provenance))| (key, item, provenance))
+            .collect();
+        ciborium::into_writer(&items, writer)?;
+        Ok(())
+    }
+
+    fn decode(&self, reader: &mut dyn Read) -> anyhow::Result<Dynamic> {
+        let items: Vec<(ArcStr, T, Provenance)> = ciborium::from_reader(reader)?;
+        let map = items
+            .into_iter()
+            .map(|(key, item, provenance)| (key, (item, provenance)))
+            .collect::<BTreeMap<_, _>>();
+        Ok(Arc::new(Map { map, dirty: false }))
+    }
+}
+
+impl<T> Persistent for Many<T>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn codec(&self) -> Arc<dyn Codec> {
+        Arc::new(ManyCodec::<T>(PhantomData))
+    }
+}
+
+/// Everything stored for a task, except for the output itself which follows
+/// the header in the same file and is written by the task's [`Codec`].
+#[derive(Serialize, Deserialize)]
+struct Header {
+    binary: Hash32,
+    name: String,
+    output_type: String,
+    fingerprint: Option<Hash32>,
+    tracking: Vec<Option<TrackerState>>,
+    importmap: ImportMap,
+    store_paths: Vec<Utf8PathBuf>,
+}
+
+/// State shared between loading the cache before a build and saving it after.
+pub(crate) struct TaskCache {
+    binary: Hash32,
+    /// File names of the entries of persisted tasks in `{cache_dir}/tasks/`.
+    keys: HashMap<NodeIndex, String>,
+    /// Input fingerprints of persisted tasks, computed before the build started.
+    fingerprints: HashMap<NodeIndex, Option<Hash32>>,
+}
+
+impl TaskCache {
+    /// Loads persisted outputs for all tasks registered with
+    /// [`Blueprint::persist`](crate::Blueprint::persist).
+    ///
+    /// Returns `None` if no task is persisted or the running executable can't
+    /// be identified. Entries that fail to load are silently ignored, the
+    /// task simply runs again.
+    pub(crate) fn load<G: Send + Sync>(
+        site: &Website<G>,
+    ) -> Option<(Self, HashMap<NodeIndex, NodeData>)> {
+        if site.persisted.is_empty() {
+            return None;
+        }
+
+        let binary = match std::env::current_exe().and_then(Hash32::hash_file) {
+            Ok(hash) => hash,
+            Err(e) => {
+                tracing::warn!("persistent cache disabled, can't hash executable: {e}");
+                return None;
+            }
+        };
+
+        let fingerprints: HashMap<_, _> = site
+            .persisted
+            .par_iter()
+            .filter_map(|(&index, _)| {
+                match site.graph[index].fingerprint(&*site.vfs, &site.stats) {
+                    Ok(fingerprint) => Some((index, fingerprint)),
+                    Err(e) => {
+                        tracing::warn!("failed to fingerprint `{}`: {e}", site.graph[index].name());
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        let keys = entry_keys(site);
+        let dir = site.cache_dir.join(DIR_TASKS);
+        let restored: HashMap<_, _> = fingerprints
+            .par_iter()
+            .filter_map(|(&index, fingerprint)| {
+                let path = dir.join(&keys[&index]);
+                let codec = &site.persisted[&index];
+                match load_node(site, &path, index, codec.as_ref(), binary, *fingerprint) {
+                    Ok(data) => data.map(|data| (index, data)),
+                    Err(e) => {
+                        let name = site.graph[index].name();
+                        tracing::debug!("failed to load persisted task `{name}`: {e}");
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        tracing::debug!(
+            "restored {} of {} persisted task(s)",
+            restored.len(),
+            site.persisted.len()
+        );
+
+        let cache = Self {
+            binary,
+            keys,
+            fingerprints,
+        };
+
+        Some((cache, restored))
+    }
+
+    /// Writes the outputs of all persisted tasks in `executed`, the tasks that
+    /// ran successfully during this build, to `{cache_dir}/tasks/`, and removes
+    /// the entries of tasks which are no longer persisted.
+    ///
+    /// Restored tasks are rewritten too if they ran again, e.g. because one of
+    /// their dependencies changed.
+    pub(crate) fn save<G: Send + Sync>(
+        &self,
+        site: &Website<G>,
+        nodes: &HashMap<NodeIndex, NodeData>,
+        executed: &HashSet<NodeIndex>,
+    ) -> anyhow::Result<()> {
+        let dir = site.cache_dir.join(DIR_TASKS);
+        fs::create_dir_all(&dir)?;
+        self.remove_stale(&dir)?;
+
+        self.fingerprints
+            .par_iter()
+            .filter(|(index, _)| executed.contains(index))
+            .try_for_each(|(&index, &fingerprint)| -> anyhow::Result<()> {
+                let Some(data) = nodes.get(&index) else {
+                    return Ok(());
+                };
+
+                let task = &site.graph[index];
+                let header = Header {
+                    binary: self.binary,
+                    name: task.name(),
+                    output_type: task.type_name_output().to_string(),
+                    fingerprint,
+                    tracking: data.tracking.clone(),
+                    importmap: data.importmap.clone(),
+                    store_paths: data.store_paths.clone(),
+                };
+
+                // Write to a temporary file first, so that an interrupted
+                // build never leaves a truncated entry behind.
+                let path = dir.join(&self.keys[&index]);
+                let temp = path.with_extension("tmp");
+                {
+                    let mut file = BufWriter::new(fs::File::create(&temp)?);
+                    ciborium::into_writer(&header, &mut file)?;
+                    site.persisted[&index].encode(&data.output, &mut file)?;
+                    file.flush()?;
+                }
+                fs::rename(&temp, &path)?;
+
+                Ok(())
+            })
+    }
+
+    /// Removes every file in `dir` which isn't the entry of a persisted task.
+    fn remove_stale(&self, dir: &Utf8Path) -> io::Result<()> {
+        let keys: HashSet<&str> = self.keys.values().map(String::as_str).collect();
+        for entry in dir.read_dir_utf8()? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() && !keys.contains(entry.file_name()) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the file name of the entry of each persisted task, derived from
+/// its name and output type. Tasks sharing both are told apart by the order
+/// in which they were added to the blueprint.
+fn entry_keys<G: Send + Sync>(site: &Website<G>) -> HashMap<NodeIndex, String> {
+    let mut indices: Vec<_> = site.persisted.keys().copied().collect();
+    indices.sort();
+
+    let mut seen = HashMap::new();
+    indices
+        .into_iter()
+        .map(|index| {
+            let task = &site.graph[index];
+            let id = format!("{}\0{}", task.name(), task.type_name_output());
+            let hash = Hash32::hash(id).to_hex();
+            let count = seen.entry(hash.clone()).or_insert(0);
+            let key = match *count {
+                0 => format!("{hash}.cbor"),
+                n => format!("{hash}-{n}.cbor"),
+            };
+            *count += 1;
+            (index, key)
+        })
+        .collect()
+}
+
+fn load_node<G: Send + Sync>(
+    site: &Website<G>,
+    path: &Utf8Path,
+    index: NodeIndex,
+    codec: &dyn Codec,
+    binary: Hash32,
+    fingerprint: Option<Hash32>,
+) -> anyhow::Result<Option<NodeData>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut reader = BufReader::new(file);
+    let header: Header = ciborium::from_reader(&mut reader)?;
+
+    let task = &site.graph[index];
+    if header.binary != binary
+        || header.name != task.name()
+        || header.output_type != task.type_name_output()
+        || header.fingerprint != fingerprint
+    {
+        return Ok(None);
+    }
+
+    for path in &header.store_paths {
+        if !restore_store_path(site, path)? {
+            tracing::debug!("persisted asset `{path}` is missing");
+            return Ok(None);
+        }
+    }
+
+    let output = codec.decode(&mut reader)?;
+
+    Ok(Some(NodeData {
+        output,
+        tracking: header.tracking,
+        importmap: header.importmap,
+        store_paths: header.store_paths,
+    }))
+}
+
+/// Makes sure that a content-addressed asset exists in `dist`, copying it back
+/// from the cache directory if needed. Returns `false` if it can't be found.
+///
+/// While staging the asset only has to exist in the cache directory.
+fn restore_store_path<G: Send + Sync>(site: &Website<G>, rel: &Utf8Path) -> io::Result<bool> {
+    let path_dist = site.out_dir.join(rel);
+    if path_dist.exists() {
+        return Ok(true);
+    }
+
+    let Some(source) = cached_store_path(&site.cache_dir, rel) else {
+        return Ok(false);
+    };
+    if site.staging {
+        return Ok(true);
+    }
+
+    if let Some(parent) = path_dist.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // hard link with fallback to copy
+    if fs::hard_link(&source, &path_dist).is_err() {
+        fs::copy(&source, &path_dist)?;
+    }
+
+    Ok(true)
+}
+
+/// Finds the copy of a content-addressed asset kept in the cache directory.
+pub(crate) fn cached_store_path(cache_dir: &Utf8Path, rel: &Utf8Path) -> Option<Utf8PathBuf> {
+    // `Store::save` publishes `hash/<hash>` from the cache as `hash/<hash>.<ext>`,
+    // while loaders such as images mirror the dist layout (`hash/img/<file>`).
+    let path = match rel.parent() {
+        Some(parent) if parent == "hash" => cache_dir.join(rel.with_extension("")),
+        _ => cache_dir.join(rel),
+    };
+
+    path.is_file().then_some(path)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_many_codec_roundtrip() {
+        let mut map = BTreeMap::new();
+        map.insert(ArcStr::from("a"), (1, Provenance(Hash32::hash(b"a"))));
+        map.insert(ArcStr::from("b"), (2, Provenance(Hash32::hash(b"b"))));
+        let output: Dynamic = Arc::new(Map { map, dirty: true });
+
+        let codec = Many::<i32>::new(NodeIndex::new(0)).codec();
+        let mut buffer = Vec::new();
+        codec.encode(&output, &mut buffer).unwrap();
+        let decoded = codec.decode(&mut buffer.as_slice()).unwrap();
+        let decoded = decoded.downcast_ref::<Map<i32>>().unwrap();
+
+        assert!(!decoded.dirty);
+        assert_eq!(decoded.map.len(), 2);
+        assert_eq!(decoded.map["b"], (2, Provenance(Hash32::hash(b"b"))));
+    }
+
+    #[test]
+    fn test_header_followed_by_output() {
+        let header = Header {
+            binary: Hash32::hash(b"binary"),
+            name: "task".into(),
+            output_type: "alloc::string::String".into(),
+            fingerprint: None,
+            tracking: vec![None],
+            importmap: ImportMap::new(),
+            store_paths: vec![Utf8PathBuf::from("hash/abc.css")],
+        };
+        let output: Dynamic = Arc::new(String::from("hello"));
+        let codec = One::<String>::new(NodeIndex::new(0)).codec();
+
+        let mut buffer = Vec::new();
+        ciborium::into_writer(&header, &mut buffer).unwrap();
+        codec.encode(&output, &mut buffer).unwrap();
+
+        let mut reader = buffer.as_slice();
+        let decoded: Header = ciborium::from_reader(&mut reader).unwrap();
+        let output = codec.decode(&mut reader).unwrap();
+
+        assert_eq!(decoded.name, "task");
+        assert_eq!(decoded.store_paths, header.store_paths);
+        assert_eq!(output.downcast_ref::<String>().unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_entry_keys() {
+        let mut config = crate::Blueprint::<()>::new();
+        let unused = config.task().run(|_| Ok(0));
+        let a = config.task().run(|_| Ok(String::from("a")));
+        let b = config.task().run(|_| Ok(String::from("b")));
+        let c = config.task().name("c").run(|_| Ok(String::from("c")));
+        config.persist(a);
+        config.persist(b);
+        config.persist(c);
+        let keys = entry_keys(&config.finish());
+
+        // Unnamed tasks of the same type are told apart by their order.
+        let a = &keys[&a.index()];
+        let c = &keys[&c.index()];
+        assert_eq!(keys[&b.index()], a.replace(".cbor", "-1.cbor"));
+        assert_ne!(c, a);
+        assert!(!keys.contains_key(&unused.index()));
+
+        // Keys don't depend on the indices of other tasks.
+        let mut config = crate::Blueprint::<()>::new();
+        let handle = config.task().name("c").run(|_| Ok(String::from("c")));
+        config.persist(handle);
+        assert_eq!(entry_keys(&config.finish())[&handle.index()], *c);
+    }
+
+    #[test]
+    fn test_save_rewrites_restored_tasks_that_ran() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        let root = TempDir::new("persist");
+        fs::write(root.join("a.txt"), "1").unwrap();
+
+        let mut config = crate::Blueprint::<()>::new()
+            .set_dir_dist(root.join("dist"))
+            .set_dir_cache(root.join("cache"));
+        let a = config
+            .task()
+            .name("a")
+            .glob(root.join("a.txt"))
+            .unwrap()
+            .map(|_, _, input| Ok(String::from_utf8(input.read()?.into())?));
+        let b = config.task().name("b").using(a).merge(|_, a| {
+            RUNS.fetch_add(1, Ordering::SeqCst);
+            Ok(a.values().cloned().collect::<String>())
+        });
+        config
+            .task()
+            .using(b)
+            .merge(|_, b| Ok(crate::Output::html("index", b.clone())));
+        config.persist(a);
+        config.persist(b);
+
+        let mut site = config.finish();
+        let mut build = || site.build(()).unwrap();
+
+        build();
+        fs::write(root.join("a.txt"), "2").unwrap();
+        build();
+        assert_eq!(RUNS.load(Ordering::SeqCst), 2);
+
+        // Both entries are up to date, nothing runs again.
+        build();
+        assert_eq!(RUNS.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            fs::read_to_string(root.join("dist/index.html")).unwrap(),
+            "2"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Resource classes limiting how many tasks of a kind run at once.
+//!
+//! Tasks declared with `.limit("heavy", 2)` share the `heavy` class. The
+//! scheduler never runs more tasks of a class at once than its limit, and
+//! each task of the class executes inside a dedicated thread pool of that
+//! size, so that the parallel item loops of loaders are bounded as well.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// A resource class requested for a single task.
+#[derive(Debug, Clone)]
+pub(crate) struct Limit {
+    pub class: Cow<'static, str>,
+    pub max: usize,
+}
+
+impl Limit {
+    pub(crate) fn new(class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        Self {
+            class: class.into(),
+            max: max.max(1),
+        }
+    }
+}
+
+/// A resource class shared by all tasks declared with the same name.
+pub(crate) struct ResourceClass {
+    pub name: Cow<'static, str>,
+    pub max: usize,
+    /// Created on first use, `None` if the pool could not be created.
+    pool: OnceLock<Option<ThreadPool>>,
+}
+
+impl ResourceClass {
+    pub(crate) fn new(name: Cow<'static, str>, max: usize) -> Self {
+        Self {
+            name,
+            max,
+            pool: OnceLock::new(),
+        }
+    }
+
+    /// Runs `op` inside the thread pool of this class, any parallel iterator
+    /// used by `op` is then limited to `max` threads.
+    pub(crate) fn install<R, F>(&self, op: F) -> R
+    where
+        R: Send,
+        F: FnOnce() -> R + Send,
+    {
+        let pool = self.pool.get_or_init(|| {
+            let name = self.name.clone();
+            ThreadPoolBuilder::new()
+                .num_threads(self.max)
+                .thread_name(move |i| format!("hauchiwa-{name}-{i}"))
+                .build()
+                .inspect_err(|e| {
+                    tracing::warn!(
+                        "failed to create pool for resource class `{}`: {e}",
+                        self.name
+                    )
+                })
+                .ok()
+        });
+
+        match pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_install_bounds_parallelism() {
+        let class = ResourceClass::new("heavy".into(), 2);
+        let threads = class.install(rayon::current_num_threads);
+        assert_eq!(threads, 2);
+
+        let sum: u32 = class.install(|| (1..=4u32).into_par_iter().sum());
+        assert_eq!(sum, 10);
+    }
+}
@@ -1,11 +1,15 @@
 mod diagnostics;
-#[cfg(feature = "server")]
+#[cfg(all(feature = "live", feature = "server"))]
 mod http;
+mod timings;
 #[cfg(feature = "live")]
 mod watch;
+#[cfg(all(feature = "live", not(feature = "server")))]
+mod ws;
 
-use std::collections::{HashMap, HashSet};
-use std::sync::mpsc::channel;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, channel};
 use std::time::{Duration, Instant};
 
 use petgraph::graph::NodeIndex;
@@ -17,10 +21,14 @@ use petgraph::Graph;
 use camino::Utf8PathBuf;
 
 use crate::core::{Dynamic, Store};
-use crate::engine::{Map, Task, TrackerState};
-use crate::snapshot::Snapshot;
+use crate::engine::persist::TaskCache;
+use crate::engine::{CancelToken, Map, Task, TrackerState};
+use crate::error::{BuildError, LoaderError};
+use crate::snapshot::{CommitReport, Snapshot};
+use crate::stat::StatCache;
 use crate::{Environment, ImportMap, Output, TaskContext, Website};
 
+pub(crate) use timings::Timings;
 #[cfg(feature = "live")]
 pub(crate) use watch::watch;
 
@@ -30,6 +38,23 @@ pub use diagnostics::Diagnostics;
 pub struct TaskExecution {
     pub start: Instant,
     pub duration: Duration,
+    /// False if the task was skipped because its previous output was still valid.
+    pub executed: bool,
+}
+
+/// Sent back to the scheduler for every task, whether executed or skipped.
+struct Completed {
+    index: NodeIndex,
+    output: anyhow::Result<NodeData>,
+    start: Instant,
+    duration: Duration,
+    executed: bool,
+    /// True if the task ran past its timeout, the output is discarded.
+    timed_out: bool,
+    /// False if the output is identical to the previous one (early cutoff).
+    changed: bool,
+    /// Files that failed to load without failing the whole task.
+    failures: Vec<LoaderError>,
 }
 
 /// Represents the data stored in the graph for each node.
@@ -48,23 +73,81 @@ pub(crate) struct NodeData {
 pub(crate) fn run_once_parallel<G: Send + Sync>(
     website: &mut Website<G>,
     globals: &Environment<G>,
+) -> Result<(HashMap<NodeIndex, NodeData>, Snapshot, Diagnostics), crate::error::HauchiwaError> {
+    let pending = website.graph.node_indices().collect();
+    run_subgraph_parallel(website, globals, pending)
+}
+
+/// Like [`run_once_parallel`], but only runs the `pending` tasks, which have
+/// to include everything they depend on, see [`find_required`].
+pub(crate) fn run_subgraph_parallel<G: Send + Sync>(
+    website: &mut Website<G>,
+    globals: &Environment<G>,
+    pending: HashSet<NodeIndex>,
 ) -> Result<(HashMap<NodeIndex, NodeData>, Snapshot, Diagnostics), crate::error::HauchiwaError> {
     // We run toposort primarily to detect any cycles in the graph.
     petgraph::algo::toposort(&website.graph, None)
         .map_err(|_| crate::error::HauchiwaError::GraphCycle)?;
 
-    let mut cache = HashMap::new();
-    let pending = website.graph.node_indices().collect();
+    website.stats = Arc::new(StatCache::load(&website.cache_dir));
+    website.timings = Arc::new(Timings::load(&website.cache_dir));
+
+    // Outputs restored from the persistent cache are treated exactly like the
+    // ones kept in memory between watch rebuilds, tasks are skipped if valid.
+    let (persisted, mut cache) = match TaskCache::load(website) {
+        Some((persisted, cache)) => (Some(persisted), cache),
+        None => (None, HashMap::new()),
+    };
     let dirty = HashSet::new();
+    let cancel = CancelToken::new();
+
+    let diagnostics = run_tasks_parallel(website, globals, &mut cache, &pending, &dirty, &cancel)?;
+
+    let executed: HashSet<_> = diagnostics
+        .execution_times
+        .iter()
+        .filter(|(index, execution)| execution.executed && !diagnostics.failed.contains(index))
+        .map(|(&index, _)| index)
+        .collect();
+
+    if let Some(persisted) = persisted
+        && let Err(e) = persisted.save(website, &cache, &executed)
+    {
+        tracing::warn!("failed to save persistent task cache: {e}");
+    }
+
+    if let Err(e) = website.stats.save(&website.cache_dir) {
+        tracing::warn!("failed to save stat cache: {e}");
+    }
 
-    let diagnostics = run_tasks_parallel(website, globals, &mut cache, &pending, &dirty)
-        .map_err(|e| crate::error::HauchiwaError::Build(crate::error::BuildError::Other(e)))?;
+    if let Err(e) = website.timings.save(&website.cache_dir) {
+        tracing::warn!("failed to save task timings: {e}");
+    }
 
     let manifest =
         collect_manifest(&cache, &website.graph).map_err(crate::error::HauchiwaError::Build)?;
     Ok((cache, manifest, diagnostics))
 }
 
+/// Waits for the next task to complete.
+///
+/// The scheduler runs on a worker thread of the pool it spawns tasks on, so
+/// it executes queued tasks itself instead of blocking, otherwise a pool with
+/// a single thread would never make progress.
+fn next_completed(receiver: &Receiver<Completed>) -> Completed {
+    loop {
+        if let Ok(completed) = receiver.try_recv() {
+            return completed;
+        }
+
+        if rayon::yield_now() != Some(rayon::Yield::Executed) {
+            #[allow(clippy::unwrap_used)]
+            // senders live in the rayon scope; recv only fails if all senders dropped
+            return receiver.recv().unwrap();
+        }
+    }
+}
+
 /// This function executes the task graph using a thread pool. It performs a
 /// parallel topological sort of the graph, where tasks are executed as soon as
 /// their dependencies are met.
@@ -73,20 +156,26 @@ pub(crate) fn run_once_parallel<G: Send + Sync>(
 /// 1. A pool of worker threads is spawned.
 /// 2. Two channels are created: one for sending tasks to the workers and one
 ///    for receiving results back.
-/// 3. The initial set of tasks (those with no dependencies) is sent to the
-///    workers.
-/// 4. The main thread enters a loop, waiting for results from the workers.
+/// 3. The initial set of tasks (those with no dependencies) is queued, ordered
+///    by the longest path from each task to the end of the graph, estimated
+///    from the durations recorded by previous runs.
+/// 4. The main thread enters a loop, sending queued tasks to the workers, at
+///    most as many at once as there are threads, and waiting for results.
 /// 5. When a task completes, its result is cached. The dependency counts of
 ///    all tasks that depend on the completed task are decremented.
-/// 6. If a task's dependency count reaches zero, it is sent to the workers.
+/// 6. If a task's dependency count reaches zero, it is queued.
 /// 7. The loop continues until all tasks have been completed.
+///
+/// Each task receives a token derived from `cancel`. Once `cancel` is
+/// cancelled, tasks that have not started yet fail immediately.
 pub(crate) fn run_tasks_parallel<G: Send + Sync>(
     site: &Website<G>,
     globals: &Environment<G>,
     cache: &mut HashMap<NodeIndex, NodeData>,
     nodes_to_run: &HashSet<NodeIndex>,
     explicitly_dirty: &HashSet<NodeIndex>,
-) -> anyhow::Result<Diagnostics> {
+    cancel: &CancelToken,
+) -> Result<Diagnostics, BuildError> {
     // Build a map from a dependency to the nodes that depend on it for the entire graph.
     let mut dependents: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
     for edge in site.graph.raw_edges() {
@@ -111,6 +200,17 @@ pub(crate) fn run_tasks_parallel<G: Send + Sync>(
         })
         .collect();
 
+    // Tasks on the critical path are started first.
+    let priorities = site.timings.critical_paths(&site.graph);
+    let priority = |index: NodeIndex| priorities.get(&index).copied().unwrap_or_default();
+
+    // Tasks are only handed to rayon once a thread is about to free up,
+    // otherwise they would run in the order they were spawned.
+    let max_in_flight = rayon::current_num_threads().max(1);
+    let mut in_flight = 0;
+    // Number of running tasks in each resource class.
+    let mut running: HashMap<&str, usize> = HashMap::new();
+
     let total_tasks = nodes_to_run.len() as u64;
     let mut completed_tasks = 0;
 
@@ -126,15 +226,16 @@ pub(crate) fn run_tasks_parallel<G: Send + Sync>(
 
     let mut execution_times = HashMap::new();
     let mut updated_nodes = HashSet::new();
+    let mut failures = Vec::new();
+    // Tasks that failed, or were skipped because one of their dependencies did.
+    let mut failed = HashSet::new();
 
     // regular task style with no progress
     let pb_style = site.progress.task.clone();
 
-    rayon::scope(|s| -> anyhow::Result<()> {
+    rayon::scope(|s| -> Result<(), BuildError> {
         // We only need a channel for results and tasks are distributed by Rayon.
-        // (index, result, start, duration, ran_was_executed)
-        let (result_sender, result_receiver) =
-            channel::<(NodeIndex, anyhow::Result<NodeData>, Instant, Duration, bool)>();
+        let (result_sender, result_receiver) = channel::<Completed>();
 
         // A helper closure to spawn a task
         let spawn_task = |cache: &HashMap<NodeIndex, NodeData>,
@@ -173,7 +274,16 @@ pub(crate) fn run_tasks_parallel<G: Send + Sync>(
                 let output = old_data.unwrap();
                 #[allow(clippy::unwrap_used)] // receiver lives for the duration of the rayon scope
                 sender
-                    .send((index, Ok(output), Instant::now(), Duration::ZERO, false))
+                    .send(Completed {
+                        index,
+                        output: Ok(output),
+                        start: Instant::now(),
+                        duration: Duration::ZERO,
+                        executed: false,
+                        timed_out: false,
+                        changed: false,
+                        failures: Vec::new(),
+                    })
                     .unwrap();
                 return;
             }
@@ -184,8 +294,10 @@ pub(crate) fn run_tasks_parallel<G: Send + Sync>(
             let sender = result_sender.clone();
             let pb_style = pb_style.clone();
 
+            let old_importmap = old_data.as_ref().map(|d| d.importmap.clone());
             let old_output = old_data.map(|d| d.output);
             let updated_nodes = updated_nodes.clone();
+            let token = cancel.child(site.timeouts.get(&index).copied());
 
             // Spawn on Rayon pool
             s.spawn(move |_| {
@@ -200,16 +312,23 @@ pub(crate) fn run_tasks_parallel<G: Send + Sync>(
                     importmap: &importmap,
                     span: span.clone(),
                     progress: &site.progress,
+                    continue_on_error: site.continue_on_error,
+                    cancel: token.clone(),
                 };
 
                 let start_time = Instant::now();
+                let mut rt = Store::with_dirs(site.out_dir.clone(), site.cache_dir.clone());
+                rt.staging = site.staging;
+                rt.stats = site.stats.clone();
+                rt.vfs = site.vfs.clone();
+                let failures = rt.failures.clone();
 
                 // We use AssertUnwindSafe because we are confident that if the
                 // specific task logic panics, it won't corrupt the shared
                 // memory in a way that affects other threads (since we are
                 // using mostly cloned and/or immutable data).
-                let output = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                    let mut rt = Store::with_dirs(site.out_dir.clone(), site.cache_dir.clone());
+                let execute = || {
+                    let mut rt = rt;
 
                     match task {
                         Task::C(task) => task.execute(&context, &mut rt, &dependencies).map(
@@ -245,6 +364,21 @@ pub(crate) fn run_tasks_parallel<G: Send + Sync>(
                                 }
                             }),
                     }
+                };
+
+                // Tasks of an abandoned build are not started at all.
+                let execute = || {
+                    token.check()?;
+                    token.enter(execute)
+                };
+
+                // Tasks in a resource class run in its thread pool, which also
+                // bounds any parallel loops inside of them.
+                let output = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    match site.limits.get(&index) {
+                        Some(class) => class.install(execute),
+                        None => execute(),
+                    }
                 })) {
                     Ok(result) => result,
                     Err(panic) => {
@@ -261,37 +395,166 @@ pub(crate) fn run_tasks_parallel<G: Send + Sync>(
                 };
 
                 let elapsed = start_time.elapsed();
+                let timed_out = token.timed_out();
+
+                // Early cutoff, an identical output doesn't invalidate dependents.
+                let changed = match (&output, &old_output, site.cutoff.get(&index)) {
+                    (Ok(new), Some(old), Some(hasher)) => {
+                        let same = old_importmap.as_ref() == Some(&new.importmap)
+                            && hasher
+                                .hash(old)
+                                .is_some_and(|old| hasher.hash(&new.output) == Some(old));
+                        !same
+                    }
+                    _ => true,
+                };
+
+                // poisoned mutex means a thread panicked - unrecoverable
+                #[allow(clippy::unwrap_used)]
+                let mut failures = std::mem::take(&mut *failures.lock().unwrap());
+                // Files skipped because of the timeout are not worth reporting.
+                if timed_out {
+                    failures.clear();
+                }
 
                 // Send result back to main thread
-                let _ = sender.send((index, output, start_time, elapsed, true));
+                let _ = sender.send(Completed {
+                    index,
+                    output,
+                    start: start_time,
+                    duration: elapsed,
+                    executed: true,
+                    timed_out,
+                    changed,
+                    failures,
+                });
             });
         };
 
         // Seed initial tasks
-        for &node_index in nodes_to_run {
-            if dependency_counts.get(&node_index).cloned().unwrap_or(0) == 0 {
-                spawn_task(cache, node_index, &updated_nodes);
-            }
-        }
+        let mut ready: BinaryHeap<(Duration, NodeIndex)> = nodes_to_run
+            .iter()
+            .copied()
+            .filter(|index| dependency_counts.get(index).cloned().unwrap_or(0) == 0)
+            .map(|index| (priority(index), index))
+            .collect();
 
         // Scheduler loop
         // The main thread sits here while Rayon workers execute tasks.
-        while completed_tasks < total_tasks {
+        loop {
+            // Spawn every task whose dependencies are satisfied. Tasks that
+            // depend on a failed task are skipped, along with their dependents.
+            // Tasks whose resource class is saturated wait for the next round.
+            let mut deferred = Vec::new();
+            while in_flight < max_in_flight
+                && let Some((estimate, index)) = ready.pop()
+            {
+                let blocked = site.graph[index]
+                    .dependencies()
+                    .iter()
+                    .any(|dep| failed.contains(dep) || !cache.contains_key(dep));
+
+                if !blocked {
+                    if let Some(class) = site.limits.get(&index) {
+                        let running = running.entry(&class.name).or_default();
+                        if *running >= class.max {
+                            deferred.push((estimate, index));
+                            continue;
+                        }
+                        *running += 1;
+                    }
+
+                    spawn_task(cache, index, &updated_nodes);
+                    in_flight += 1;
+                    continue;
+                }
+
+                tracing::warn!(
+                    "skipping `{}`, a dependency failed",
+                    site.graph[index].name()
+                );
+                failed.insert(index);
+                completed_tasks += 1;
+                root_span.pb_inc(1);
+                unlock_dependents(&dependents, &mut dependency_counts, index, |index| {
+                    ready.push((priority(index), index))
+                });
+            }
+            ready.extend(deferred);
+
+            if completed_tasks >= total_tasks {
+                break;
+            }
+
             // Wait for any task to finish
-            #[allow(clippy::unwrap_used)]
-            // senders live in the rayon scope above; recv only fails if all senders dropped
-            let (completed_index, output, start, duration, executed) =
-                result_receiver.recv().unwrap();
+            let Completed {
+                index: completed_index,
+                output,
+                start,
+                duration,
+                executed,
+                timed_out,
+                changed,
+                failures: file_failures,
+            } = next_completed(&result_receiver);
+            in_flight -= 1;
+            if let Some(class) = site.limits.get(&completed_index)
+                && let Some(running) = running.get_mut(&*class.name)
+            {
+                *running -= 1;
+            }
+
+            let task = &site.graph[completed_index];
+
+            // Files skipped by loaders, the rest of the output is still valid.
+            for failure in file_failures {
+                let error = BuildError::Task(task.name(), failure.into());
+                tracing::error!("{error}");
+                failures.push(error);
+            }
+
+            // A task that ran past its timeout fails, even if it finished.
+            let output = match site.timeouts.get(&completed_index) {
+                Some(&timeout) if timed_out => Err(BuildError::Timeout(task.name(), timeout)),
+                _ => output.map_err(|e| BuildError::Task(task.name(), e)),
+            };
 
             // Update state
-            cache.insert(completed_index, output?);
-            execution_times.insert(completed_index, TaskExecution { start, duration });
+            match output {
+                Ok(output) => {
+                    cache.insert(completed_index, output);
+                }
+                Err(error) => {
+                    if !site.continue_on_error {
+                        return Err(error);
+                    }
+
+                    // The old output, if any, stays in the cache so that
+                    // the pages it produced are not removed from dist.
+                    tracing::error!("{error}");
+                    failures.push(error);
+                    failed.insert(completed_index);
+                }
+            }
+            execution_times.insert(
+                completed_index,
+                TaskExecution {
+                    start,
+                    duration,
+                    executed,
+                },
+            );
             completed_tasks += 1;
             root_span.pb_inc(1);
 
             if executed {
-                updated_nodes.insert(completed_index);
-                let task = &site.graph[completed_index];
+                site.timings.record(completed_index, task.name(), duration);
+            }
+
+            if executed && !failed.contains(&completed_index) {
+                if changed {
+                    updated_nodes.insert(completed_index);
+                }
                 tracing::info!(
                     target: "task",
                     name = task.name(),
@@ -301,24 +564,91 @@ pub(crate) fn run_tasks_parallel<G: Send + Sync>(
             }
 
             // Unlock dependents
-            if let Some(dependents_of_completed) = dependents.get(&completed_index) {
-                for &index in dependents_of_completed {
-                    if let Some(count) = dependency_counts.get_mut(&index) {
-                        *count -= 1;
-                        if *count == 0 {
-                            // Dependency satisfied, spawn immediately
-                            spawn_task(cache, index, &updated_nodes);
-                        }
-                    }
-                }
-            }
+            unlock_dependents(
+                &dependents,
+                &mut dependency_counts,
+                completed_index,
+                |index| ready.push((priority(index), index)),
+            );
         }
 
         Ok(())
     })?;
 
     tracing::info!("Build complete!");
-    Ok(Diagnostics { execution_times })
+    Ok(Diagnostics {
+        execution_times,
+        failures,
+        failed,
+        commit: CommitReport::default(),
+    })
+}
+
+/// Returns the tasks that watch `path`, which must be given in the same form
+/// as the globs of the tasks, e.g. relative to the working directory.
+#[cfg(any(test, feature = "live", feature = "testing"))]
+pub(crate) fn find_dirty<G: Send + Sync>(
+    site: &Website<G>,
+    path: &camino::Utf8Path,
+) -> impl Iterator<Item = NodeIndex> {
+    site.graph
+        .node_indices()
+        .filter(move |&index| site.graph[index].is_dirty(path))
+}
+
+/// Returns the `dirty` tasks together with every task that transitively
+/// depends on one of them, i.e. the tasks an incremental rebuild runs again.
+#[cfg(any(test, feature = "live", feature = "testing"))]
+pub(crate) fn find_affected<G: Send + Sync>(
+    site: &Website<G>,
+    dirty: &HashSet<NodeIndex>,
+) -> HashSet<NodeIndex> {
+    let mut affected = HashSet::new();
+    for &start in dirty {
+        let mut dfs = petgraph::visit::Dfs::new(&site.graph, start);
+        while let Some(index) = dfs.next(&site.graph) {
+            affected.insert(index);
+        }
+    }
+    affected
+}
+
+/// Returns the `targets` together with every task they transitively depend
+/// on, i.e. the tasks that have to run to build the targets.
+pub(crate) fn find_required<G: Send + Sync>(
+    site: &Website<G>,
+    targets: impl IntoIterator<Item = NodeIndex>,
+) -> HashSet<NodeIndex> {
+    let reversed = petgraph::visit::Reversed(&site.graph);
+    let mut required = HashSet::new();
+    for start in targets {
+        let mut dfs = petgraph::visit::Dfs::new(reversed, start);
+        while let Some(index) = dfs.next(reversed) {
+            required.insert(index);
+        }
+    }
+    required
+}
+
+/// Decrements the dependency counts of all dependents of `index`, queueing the
+/// ones that have all dependencies satisfied.
+fn unlock_dependents(
+    dependents: &HashMap<NodeIndex, Vec<NodeIndex>>,
+    dependency_counts: &mut HashMap<NodeIndex, usize>,
+    index: NodeIndex,
+    mut ready: impl FnMut(NodeIndex),
+) {
+    if let Some(dependents_of_completed) = dependents.get(&index) {
+        for &index in dependents_of_completed {
+            if let Some(count) = dependency_counts.get_mut(&index) {
+                *count -= 1;
+                if *count == 0 {
+                    // Dependency satisfied
+                    ready(index);
+                }
+            }
+        }
+    }
 }
 
 pub(crate) fn collect_manifest<G: Send + Sync>(
@@ -348,3 +678,137 @@ pub(crate) fn collect_manifest<G: Send + Sync>(
     }
     Ok(manifest)
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    use crate::Blueprint;
+    use crate::test_utils::ENV;
+
+    fn run(
+        site: &Website,
+        cache: &mut HashMap<NodeIndex, NodeData>,
+    ) -> Result<Diagnostics, BuildError> {
+        run_dirty(site, cache, &HashSet::new())
+    }
+
+    fn run_dirty(
+        site: &Website,
+        cache: &mut HashMap<NodeIndex, NodeData>,
+        dirty: &HashSet<NodeIndex>,
+    ) -> Result<Diagnostics, BuildError> {
+        let pending = site.graph.node_indices().collect();
+        run_tasks_parallel(site, &ENV, cache, &pending, dirty, &CancelToken::new())
+    }
+
+    #[test]
+    fn test_single_thread_pool() {
+        let mut config = Blueprint::<()>::new();
+        let a = config.task().run(|_| Ok(1u32));
+        let b = config.task().run(|_| Ok(2u32));
+        let sum = config.task().using((a, b)).merge(|_, (a, b)| Ok(*a + *b));
+        let site = config.finish();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let mut cache = HashMap::new();
+        pool.install(|| run(&site, &mut cache)).unwrap();
+
+        let output = cache[&sum.index()].output.downcast_ref::<u32>();
+        assert_eq!(output, Some(&3));
+    }
+
+    #[test]
+    fn test_continue_on_error_skips_dependents() {
+        let mut config = Blueprint::<()>::new().set_continue_on_error(true);
+        let broken = config
+            .task()
+            .name("broken")
+            .run(|_| -> anyhow::Result<u32> { anyhow::bail!("oops") });
+        let dependent = config.task().using(broken).merge(|_, value| Ok(*value + 1));
+        let independent = config.task().run(|_| Ok(1u32));
+        let site = config.finish();
+
+        let mut cache = HashMap::new();
+        let diagnostics = run(&site, &mut cache).unwrap();
+
+        assert_eq!(diagnostics.failures.len(), 1);
+        assert!(matches!(
+            &diagnostics.failures[0],
+            BuildError::Task(name, _) if name == "broken"
+        ));
+        assert!(!cache.contains_key(&dependent.index()));
+        assert!(cache.contains_key(&independent.index()));
+        assert_eq!(
+            diagnostics.failed,
+            HashSet::from([broken.index(), dependent.index()])
+        );
+    }
+
+    #[test]
+    fn test_failed_task_keeps_old_output() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut config = Blueprint::<()>::new().set_continue_on_error(true);
+        let flaky = config
+            .task()
+            .run(|_| match RUNS.fetch_add(1, Ordering::SeqCst) {
+                1 => anyhow::bail!("oops"),
+                n => Ok(n),
+            });
+        let site = config.finish();
+        let flaky = HashSet::from([flaky.index()]);
+
+        let mut cache = HashMap::new();
+        run(&site, &mut cache).unwrap();
+        let diagnostics = run_dirty(&site, &mut cache, &flaky).unwrap();
+        assert_eq!(diagnostics.failed, flaky);
+
+        // The old output is still valid, so only marking it dirty retries it.
+        run(&site, &mut cache).unwrap();
+        assert_eq!(RUNS.load(Ordering::SeqCst), 2);
+        let diagnostics = run_dirty(&site, &mut cache, &diagnostics.failed).unwrap();
+        assert!(diagnostics.failed.is_empty());
+        assert_eq!(RUNS.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_fail_fast_reports_task() {
+        let mut config = Blueprint::<()>::new();
+        config
+            .task()
+            .name("broken")
+            .run(|_| -> anyhow::Result<u32> { anyhow::bail!("oops") });
+        let site = config.finish();
+
+        let result = run(&site, &mut HashMap::new());
+
+        assert!(matches!(result, Err(BuildError::Task(name, _)) if name == "broken"));
+    }
+
+    #[test]
+    fn test_timeout_reports_task() {
+        let mut config = Blueprint::<()>::new();
+        config
+            .task()
+            .name("hung")
+            .timeout(Duration::from_millis(50))
+            .run(|ctx| {
+                while !ctx.cancel_token().is_cancelled() {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                Ok(())
+            });
+        let site = config.finish();
+
+        let result = run(&site, &mut HashMap::new());
+
+        assert!(matches!(result, Err(BuildError::Timeout(name, _)) if name == "hung"));
+    }
+}
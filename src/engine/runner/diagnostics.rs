@@ -1,12 +1,14 @@
 #![allow(clippy::unwrap_used)] // writeln! on String is infallible; edge_endpoints on valid indices; from_tasks after is_empty check
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter, Write};
 
 use petgraph::graph::NodeIndex;
 
 use crate::Website;
 use crate::engine::TaskExecution;
+use crate::error::BuildError;
+use crate::snapshot::CommitReport;
 
 /// Build diagnostics and performance metrics.
 ///
@@ -16,6 +18,18 @@ use crate::engine::TaskExecution;
 pub struct Diagnostics {
     /// A map of task node indices to their execution metrics.
     pub execution_times: HashMap<NodeIndex, TaskExecution>,
+    /// Every task that failed during the build, as [`BuildError::Task`] or
+    /// [`BuildError::Timeout`].
+    ///
+    /// Only populated when the website is configured with
+    /// [`Blueprint::set_continue_on_error`](crate::Blueprint::set_continue_on_error),
+    /// otherwise the build stops at the first failure.
+    pub failures: Vec<BuildError>,
+    /// Tasks that failed, or were skipped because a dependency failed. Their
+    /// previous output is kept, watch mode runs them again on the next change.
+    pub(crate) failed: HashSet<NodeIndex>,
+    /// The files written to and removed from `dist` by the build.
+    pub commit: CommitReport,
 }
 
 impl Diagnostics {
@@ -1,29 +1,365 @@
-use std::{net::SocketAddr, thread};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
 
+use anyhow::Context;
 use axum::Router;
-use tower_http::services::ServeDir;
+use axum::body::Body;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use camino::Utf8PathBuf;
+use tokio::sync::broadcast;
+use tower_http::services::{ServeDir, ServeFile};
 use tracing::info;
 
-pub fn start(dist: String) -> thread::JoinHandle<Result<(), anyhow::Error>> {
-    let port = 8080;
+use crate::ServerConfig;
+use crate::server::{RELOAD_PATH, ReloadMessage};
 
-    info!(url = %format!("http://localhost:{port}/"), "starting a HTTP server");
+/// Binds the HTTP server to the configured address and serves `dist` from a
+/// separate thread, returns the port the server listens on.
+pub fn start(
+    config: &ServerConfig,
+    dist: Utf8PathBuf,
+    reload: Reload,
+) -> anyhow::Result<(u16, thread::JoinHandle<Result<(), anyhow::Error>>)> {
+    let address = SocketAddr::new(config.host, config.port);
+    let listener = std::net::TcpListener::bind(address)
+        .with_context(|| format!("couldn't bind the HTTP server to {address}"))?;
+    listener.set_nonblocking(true)?;
 
-    thread::spawn(move || {
+    let router = router(config, dist, reload)?;
+
+    let address = listener.local_addr()?;
+    let url = if address.ip().is_unspecified() {
+        format!("http://localhost:{}/", address.port())
+    } else {
+        format!("http://{address}/")
+    };
+    info!(url = %url, "starting a HTTP server");
+
+    let thread = thread::spawn(move || {
         tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()?
-            .block_on(serve(port, dist))
-    })
-}
+            .block_on(serve(listener, router))
+    });
 
-async fn serve(port: u16, dist: String) -> Result<(), anyhow::Error> {
-    let address = SocketAddr::from(([127, 0, 0, 1], port));
-    let address = tokio::net::TcpListener::bind(address).await?;
+    Ok((address.port(), thread))
+}
 
-    let router = Router::new().fallback_service(ServeDir::new(dist));
+async fn serve(listener: std::net::TcpListener, router: Router) -> Result<(), anyhow::Error> {
+    let listener = tokio::net::TcpListener::from_std(listener)?;
 
-    axum::serve(address, router).await?;
+    axum::serve(listener, router).await?;
 
     Ok(())
 }
+
+/// Sends messages to every page connected to [`RELOAD_PATH`].
+#[derive(Clone)]
+pub struct Reload {
+    sender: broadcast::Sender<String>,
+    /// Errors of the last rebuild, also sent to pages connecting later.
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl Reload {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(16).0,
+            error: Arc::default(),
+        }
+    }
+
+    pub fn send(&self, message: &ReloadMessage) {
+        let json = message.to_json();
+        if let Ok(mut error) = self.error.lock() {
+            *error = matches!(message, ReloadMessage::Error { .. }).then(|| json.clone());
+        }
+        // Fails only when no page is connected.
+        self.sender.send(json).ok();
+    }
+}
+
+/// Accepts a WebSocket connection and forwards the messages sent through
+/// [`Reload`] to it, messages from the page are discarded.
+async fn reload_socket(State(reload): State<Reload>, upgrade: WebSocketUpgrade) -> Response {
+    let messages = reload.sender.subscribe();
+    let error = reload.error.lock().ok().and_then(|error| error.clone());
+    upgrade.on_upgrade(move |socket| forward(socket, error, messages))
+}
+
+async fn forward(
+    mut socket: WebSocket,
+    error: Option<String>,
+    mut messages: broadcast::Receiver<String>,
+) {
+    let mut next = error;
+    loop {
+        let message = match next.take() {
+            Some(message) => message,
+            None => tokio::select! {
+                message = messages.recv() => match message {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                incoming = socket.recv() => match incoming {
+                    Some(Ok(_)) => continue,
+                    Some(Err(_)) | None => break,
+                },
+            },
+        };
+
+        if socket.send(Message::text(message)).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Pages served for paths with no matching file in `dist`.
+struct Fallback {
+    fallback: Option<Utf8PathBuf>,
+    not_found: Option<Utf8PathBuf>,
+}
+
+fn router(config: &ServerConfig, dist: Utf8PathBuf, reload: Reload) -> anyhow::Result<Router> {
+    let headers = config
+        .headers
+        .iter()
+        .map(|(name, value)| {
+            let name = HeaderName::try_from(name.as_str())
+                .with_context(|| format!("invalid header name `{name}`"))?;
+            let value = HeaderValue::try_from(value.as_str())
+                .with_context(|| format!("invalid value of header `{name}`"))?;
+            Ok((name, value))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let headers = Arc::new(headers);
+
+    let fallback = Arc::new(Fallback {
+        fallback: config.fallback.as_ref().map(|path| dist.join(path)),
+        not_found: config.not_found.as_ref().map(|path| dist.join(path)),
+    });
+
+    let files =
+        ServeDir::new(dist).fallback(axum::routing::any(serve_fallback).with_state(fallback));
+
+    let router = Router::new()
+        .route_service(
+            RELOAD_PATH,
+            axum::routing::get(reload_socket).with_state(reload),
+        )
+        .fallback_service(files)
+        .layer(axum::middleware::map_response(
+            move |mut response: Response| {
+                let headers = headers.clone();
+                async move {
+                    for (name, value) in headers.iter() {
+                        response.headers_mut().insert(name.clone(), value.clone());
+                    }
+                    response
+                }
+            },
+        ))
+        .layer(axum::middleware::map_response(inject_refresh_script));
+
+    Ok(router)
+}
+
+/// Adds the live-reload script to HTML pages, the pages in `dist` don't
+/// include it.
+async fn inject_refresh_script(response: Response) -> Response {
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    let is_partial = matches!(
+        response.status(),
+        StatusCode::PARTIAL_CONTENT | StatusCode::NOT_MODIFIED
+    );
+    if !is_html || is_partial {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let html = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(html) => html,
+        Err(e) => {
+            tracing::warn!("failed to read HTML response: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let html = inject(&html, &crate::server::refresh_script(None));
+    Response::from_parts(parts, Body::from(html))
+}
+
+/// Inserts `script` before the closing `</head>` tag, or `</body>` if there
+/// is no head, or at the end of the document.
+fn inject(html: &[u8], script: &str) -> Vec<u8> {
+    let find = |tag: &[u8]| {
+        html.windows(tag.len())
+            .position(|window| window.eq_ignore_ascii_case(tag))
+    };
+    let at = find(b"</head>")
+        .or_else(|| find(b"</body>"))
+        .unwrap_or(html.len());
+
+    let mut out = Vec::with_capacity(html.len() + script.len() + 17);
+    out.extend_from_slice(&html[..at]);
+    out.extend_from_slice(b"<script>");
+    out.extend_from_slice(script.as_bytes());
+    out.extend_from_slice(b"</script>");
+    out.extend_from_slice(&html[at..]);
+    out
+}
+
+async fn serve_fallback(State(pages): State<Arc<Fallback>>, request: Request) -> Response {
+    let (page, status) = match (&pages.fallback, &pages.not_found) {
+        (Some(page), _) => (page, StatusCode::OK),
+        (None, Some(page)) => (page, StatusCode::NOT_FOUND),
+        (None, None) => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    match ServeFile::new(page).try_call(request).await {
+        Ok(mut response) if response.status().is_success() => {
+            *response.status_mut() = status;
+            response.into_response()
+        }
+        Ok(_) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            tracing::warn!("failed to serve {page}: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use camino::Utf8Path;
+
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn get(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn spawn(config: ServerConfig, dist: &Utf8Path, reload: Reload) -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = router(&config, dist.to_owned(), reload).unwrap();
+        thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(serve(listener, router))
+        });
+        address
+    }
+
+    #[test]
+    fn test_not_found_and_fallback() {
+        let dist = TempDir::new("http");
+        std::fs::write(dist.join("index.html"), "<body>index</body>").unwrap();
+        std::fs::write(dist.join("404.html"), "<body>missing</body>").unwrap();
+        std::fs::write(dist.join("style.css"), "body {}").unwrap();
+
+        let config = ServerConfig {
+            headers: vec![("X-Test".into(), "yes".into())],
+            ..ServerConfig::default()
+        };
+        let address = spawn(config, &dist, Reload::new());
+
+        let response = get(address, "/");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.to_lowercase().contains("x-test: yes"));
+        assert!(response.contains("index<script>"));
+        assert!(response.contains(RELOAD_PATH));
+
+        let response = get(address, "/nope/");
+        assert!(response.starts_with("HTTP/1.1 404"));
+        assert!(response.contains("missing<script>"));
+
+        let response = get(address, "/style.css");
+        assert!(response.ends_with("\r\n\r\nbody {}"));
+
+        let config = ServerConfig {
+            fallback: Some("index.html".into()),
+            ..ServerConfig::default()
+        };
+        let address = spawn(config, &dist, Reload::new());
+
+        let response = get(address, "/app/route");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("index<script>"));
+    }
+
+    #[test]
+    fn test_inject() {
+        assert_eq!(
+            inject(b"<HEAD></HEAD><body></body>", "x"),
+            b"<HEAD><script>x</script></HEAD><body></body>"
+        );
+        assert_eq!(
+            inject(b"<body></body>", "x"),
+            b"<body><script>x</script></body>"
+        );
+        assert_eq!(inject(b"<p>", "x"), b"<p><script>x</script>");
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let config = ServerConfig {
+            headers: vec![("Bad Name".into(), "x".into())],
+            ..ServerConfig::default()
+        };
+        assert!(router(&config, "dist".into(), Reload::new()).is_err());
+    }
+
+    #[test]
+    fn test_reload_socket() {
+        let reload = Reload::new();
+        let address = spawn(ServerConfig::default(), "dist".into(), reload.clone());
+
+        let url = format!("ws://{address}{RELOAD_PATH}");
+        let (mut socket, _) = tungstenite::connect(url).unwrap();
+        let message = ReloadMessage::all();
+        reload.send(&message);
+        assert_eq!(
+            socket.read().unwrap(),
+            tungstenite::Message::text(message.to_json())
+        );
+
+        // Errors are also sent to pages connecting after the rebuild.
+        let error = ReloadMessage::failed(&[crate::error::BuildError::Other(anyhow::anyhow!("x"))]);
+        reload.send(&error);
+        let (mut late, _) = tungstenite::connect(format!("ws://{address}{RELOAD_PATH}")).unwrap();
+        assert_eq!(
+            late.read().unwrap(),
+            tungstenite::Message::text(error.to_json())
+        );
+
+        let response = get(address, RELOAD_PATH);
+        assert!(response.starts_with("HTTP/1.1 400"));
+    }
+}
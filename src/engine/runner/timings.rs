@@ -0,0 +1,144 @@
+//! Task durations persisted between runs, used to prioritise the critical path.
+//!
+//! The scheduler can only run as many tasks at once as there are threads, so
+//! the order in which ready tasks are started matters. A long task started
+//! late extends the whole build. Using the durations from previous runs, each
+//! task is assigned the length of the longest path from it to the end of the
+//! graph, and ready tasks are started in descending order of that length.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use camino::Utf8Path;
+use petgraph::Graph;
+use petgraph::graph::NodeIndex;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::Task;
+
+/// Estimate used for tasks that never ran before, so that on a cold build
+/// tasks with more work downstream still go first.
+const DEFAULT_ESTIMATE: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TimingEntry {
+    /// Name of the task, an entry is ignored if the task at its index changed.
+    name: String,
+    duration: Duration,
+}
+
+/// Durations of the last execution of every task, keyed by node index.
+#[derive(Default)]
+pub(crate) struct Timings {
+    durations: Mutex<HashMap<usize, TimingEntry>>,
+}
+
+impl Timings {
+    const RELATIVE_PATH: &'static str = "timings/durations.cbor";
+
+    /// Loads durations recorded by previous runs from
+    /// `{cache_dir}/timings/durations.cbor`.
+    pub(crate) fn load(cache_dir: &Utf8Path) -> Self {
+        let path = cache_dir.join(Self::RELATIVE_PATH);
+        let durations = match fs::File::open(&path) {
+            Ok(file) => ciborium::from_reader(io::BufReader::new(file)).unwrap_or_else(|e| {
+                tracing::warn!("failed to read task timings {}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            durations: Mutex::new(durations),
+        }
+    }
+
+    /// Persists the recorded durations to `{cache_dir}/timings/durations.cbor`.
+    pub(crate) fn save(&self, cache_dir: &Utf8Path) -> io::Result<()> {
+        #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+        let durations = self.durations.lock().unwrap();
+        fs::create_dir_all(cache_dir.join("timings"))?;
+        let file = fs::File::create(cache_dir.join(Self::RELATIVE_PATH))?;
+        ciborium::into_writer(&*durations, io::BufWriter::new(file)).map_err(io::Error::other)
+    }
+
+    /// Records the duration of a task that was just executed.
+    pub(crate) fn record(&self, index: NodeIndex, name: String, duration: Duration) {
+        #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+        self.durations
+            .lock()
+            .unwrap()
+            .insert(index.index(), TimingEntry { name, duration });
+    }
+
+    /// Computes, for every task, the estimated length of the longest path
+    /// from the start of the task to the end of the build.
+    pub(crate) fn critical_paths<G>(
+        &self,
+        graph: &Graph<Task<G>, ()>,
+    ) -> HashMap<NodeIndex, Duration>
+    where
+        G: Send + Sync,
+    {
+        let Ok(order) = petgraph::algo::toposort(graph, None) else {
+            return HashMap::new();
+        };
+
+        #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+        let durations = self.durations.lock().unwrap();
+
+        let mut paths = HashMap::with_capacity(order.len());
+        for index in order.into_iter().rev() {
+            let estimate = durations
+                .get(&index.index())
+                .filter(|entry| entry.name == graph[index].name())
+                .map_or(DEFAULT_ESTIMATE, |entry| entry.duration);
+
+            let downstream = graph
+                .neighbors_directed(index, petgraph::Direction::Outgoing)
+                .filter_map(|dependent| paths.get(&dependent).copied())
+                .max()
+                .unwrap_or_default();
+
+            paths.insert(index, estimate + downstream);
+        }
+
+        paths
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    use crate::Blueprint;
+
+    #[test]
+    fn test_critical_path_prefers_long_chain() {
+        let mut config = Blueprint::<()>::new();
+        let slow = config.task().name("slow").run(|_| Ok(1u32));
+        let fast = config.task().name("fast").run(|_| Ok(1u32));
+        let after = config
+            .task()
+            .name("after")
+            .using(slow)
+            .merge(|_, value| Ok(*value));
+        let site = config.finish();
+
+        let timings = Timings::default();
+        timings.record(slow.index(), "slow".into(), Duration::from_secs(2));
+        timings.record(after.index(), "after".into(), Duration::from_secs(2));
+        // entry recorded for a different task, falls back to the default
+        timings.record(fast.index(), "renamed".into(), Duration::from_secs(9));
+
+        let paths = timings.critical_paths(&site.graph);
+
+        assert_eq!(paths[&slow.index()], Duration::from_secs(4));
+        assert_eq!(paths[&fast.index()], DEFAULT_ESTIMATE);
+        assert_eq!(paths[&after.index()], Duration::from_secs(2));
+    }
+}
@@ -3,11 +3,14 @@
 //! 1. **File watcher**: Uses the `notify` crate to monitor filesystem
 //!    events recursively. It includes debouncing to prevent duplicate builds
 //!    from rapid file saves.
-//! 2. **WebSocket server**: Spawns a dedicated thread using `tungstenite`
-//!    to maintain persistent connections with open browser tabs.
-//! 3. **Client script**: The [`Environment`](crate::Environment) injects
-//!    a lightweight JavaScript snippet into generated pages. This script
-//!    connects to the WebSocket server and listens for a `"reload"` message.
+//! 2. **WebSocket server**: Maintains persistent connections with open
+//!    browser tabs. With the `server` feature it's a route of the HTTP
+//!    server serving `dist`, otherwise a dedicated thread using `tungstenite`
+//!    listening on its own port.
+//! 3. **Client script**: A lightweight JavaScript snippet, injected into
+//!    pages by the HTTP server or by [`Environment`](crate::Environment).
+//!    This script connects to the WebSocket server and listens for reload
+//!    messages listing the changed files in `dist`.
 //!
 //! ## The Loop
 //!
@@ -16,18 +19,28 @@
 //!    (incremental build).
 //! 2. Upon successful completion, the executor signals the WebSocket
 //!    thread.
-//! 3. The server broadcasts the reload command to all connected clients,
-//!    triggering an immediate browser refresh.
-
-use crate::engine::{collect_manifest, run_once_parallel, run_tasks_parallel};
+//! 3. The server broadcasts the changed paths to all connected clients, the
+//!    pages among them refresh immediately, or only swap their stylesheets
+//!    if nothing else changed.
+//!
+//! A change detected while a rebuild is still running cancels it, the
+//! affected tasks then run again together with the new changes.
+
+#[cfg(feature = "server")]
+use super::http::Reload;
+#[cfg(not(feature = "server"))]
+use super::ws::Reload;
+use crate::engine::{
+    CancelToken, collect_manifest, find_affected, find_dirty, run_once_parallel, run_tasks_parallel,
+};
+use crate::error::BuildError;
+use crate::server::ReloadMessage;
+use crate::snapshot::SnapshotView;
 use crate::{Environment, Mode, Website};
 
 use std::collections::HashSet;
 use std::env;
-use std::net::{TcpListener, TcpStream};
-use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
-use std::thread::JoinHandle;
 use std::time::Duration;
 
 use camino::{Utf8Path, Utf8PathBuf};
@@ -36,7 +49,6 @@ use glob::Pattern;
 use notify::RecursiveMode;
 use notify_debouncer_full::{DebounceEventResult, new_debouncer};
 use petgraph::visit::IntoNodeReferences;
-use tungstenite::WebSocket;
 
 pub fn watch<G: Send + Sync>(
     site: &mut Website<G>,
@@ -45,7 +57,7 @@ pub fn watch<G: Send + Sync>(
     out_dir: &Utf8Path,
     cache_dir: &Utf8Path,
 ) -> anyhow::Result<()> {
-    let (tcp, port) = reserve_port()?;
+    let (reload, port) = start_live_reload(site, out_dir)?;
     let pwd = env::current_dir()?;
 
     let globals = Environment {
@@ -59,32 +71,33 @@ pub fn watch<G: Send + Sync>(
         .ok()
         .flatten();
 
-    let mut static_files = crate::utils::collect_static(&copied, out_dir)?;
+    let mut static_files = crate::utils::collect_static(&copied, out_dir, &*site.vfs)?;
 
-    let (mut cache, mut snapshot, _) = run_once_parallel(site, &globals)?;
+    site.hooks.before_build(&globals)?;
+    let (mut cache, mut snapshot, diagnostics) = run_once_parallel(site, &globals)?;
     for entry in &static_files {
-        snapshot.insert_static_file(entry.dist_rel.clone(), entry.source_utf8.clone())?;
+        snapshot.insert_static_file(entry.dist_rel.clone(), entry.source.clone())?;
     }
-    crate::utils::copy_static_entries(&static_files, &site.progress.copy)?;
+    crate::utils::copy_static_entries(&static_files, &site.progress.copy, &*site.vfs)?;
     tracing::info!("collected {} pages", snapshot.page_count());
-    match prev_meta {
+    site.hooks
+        .after_tasks(&globals, &SnapshotView::new(&snapshot))?;
+    let report = match prev_meta {
         Some(ref prev) => snapshot.commit_diff_meta(prev, out_dir)?,
         None => snapshot.commit(out_dir)?,
-    }
+    };
     snapshot.to_meta().save(cache_dir)?;
+    site.hooks.after_commit(&globals, &report)?;
     let mut prev_snapshot = snapshot;
 
-    tracing::info!("initial build completed, now watching for changes...");
-    let clients = Arc::new(Mutex::new(vec![]));
-
-    let _thread_i = new_thread_ws_incoming(tcp, clients.clone());
-    let (tx_reload, _thread_o) = new_thread_ws_reload(clients.clone());
-
-    let (tx, rx) = std::sync::mpsc::channel::<DebounceEventResult>();
-    let mut debouncer = new_debouncer(Duration::from_millis(250), None, move |result| {
-        tx.send(result).ok();
-    })?;
+    // Tasks which failed keep their old output, they run again with every
+    // change until they succeed.
+    let mut failed = diagnostics.failed;
+    if !diagnostics.failures.is_empty() {
+        reload.send(&ReloadMessage::failed(&diagnostics.failures));
+    }
 
+    tracing::info!("initial build completed, now watching for changes...");
     let mut watched = HashSet::new();
     let mut task_filters = HashSet::new();
     let mut static_filters = HashSet::new();
@@ -111,21 +124,51 @@ pub fn watch<G: Send + Sync>(
     // Collapse watched paths to reduce the number of watches
     let watched = collapse_watch_paths(watched);
 
+    // Cancelled by the watcher thread when a relevant file changes, so that
+    // the rebuild in progress is abandoned instead of finishing first.
+    let building = Arc::new(Mutex::new(CancelToken::new()));
+
+    let (tx, rx) = std::sync::mpsc::channel::<DebounceEventResult>();
+    let on_event = {
+        let building = building.clone();
+        let filters: Vec<_> = task_filters
+            .iter()
+            .chain(&static_filters)
+            .cloned()
+            .collect();
+
+        move |result: DebounceEventResult| {
+            let relevant = result.as_ref().is_ok_and(|events| {
+                events
+                    .iter()
+                    .flat_map(|de| &de.event.paths)
+                    .any(|path| filters.iter().any(|filter| filter.matches_path(path)))
+            });
+
+            if relevant && let Ok(token) = building.lock() {
+                token.cancel();
+            }
+            tx.send(result).ok();
+        }
+    };
+    let mut debouncer = new_debouncer(Duration::from_millis(250), None, on_event)?;
+
     for path in watched {
         tracing::info!("watching {}", path);
         debouncer.watch(path, RecursiveMode::Recursive)?;
     }
 
-    #[cfg(feature = "server")]
-    let _thread_http = super::http::start(out_dir.to_string());
+    // Work left over from a cancelled rebuild, merged into the next one.
+    let mut pending_dirty = HashSet::new();
+    let mut pending_static = false;
 
     loop {
         match rx.recv() {
             Ok(Ok(events)) => {
                 tracing::debug!("{:?} events received", events);
 
-                let mut dirty_nodes = HashSet::new();
-                let mut static_dirty = false;
+                let mut dirty_nodes = std::mem::take(&mut pending_dirty);
+                let mut static_dirty = std::mem::take(&mut pending_static);
                 for de in events {
                     for path in &de.event.paths {
                         let task_match =
@@ -144,86 +187,143 @@ pub fn watch<G: Send + Sync>(
                             let Ok(path) = path.strip_prefix(&pwd) else {
                                 continue;
                             };
-                            for index in site.graph.node_indices() {
-                                let task = &site.graph[index];
-                                if task.is_dirty(path) {
-                                    dirty_nodes.insert(index);
-                                }
-                            }
+                            dirty_nodes.extend(find_dirty(site, path));
                         }
                     }
                 }
 
                 if !dirty_nodes.is_empty() || static_dirty {
                     tracing::info!("change detected, re-running tasks...");
-                    let mut to_rerun = HashSet::new();
-                    for start_node in &dirty_nodes {
-                        let mut dfs = petgraph::visit::Dfs::new(&site.graph, *start_node);
-                        while let Some(nx) = dfs.next(&site.graph) {
-                            to_rerun.insert(nx);
-                        }
+                    dirty_nodes.extend(failed.iter().copied());
+                    let to_rerun = find_affected(site, &dirty_nodes);
+
+                    // Nothing ran yet, the changes are retried with the next event.
+                    if let Err(e) = site.hooks.before_build(&globals) {
+                        report_failure(&reload, e);
+                        pending_dirty = dirty_nodes;
+                        pending_static = static_dirty;
+                        continue;
                     }
 
+                    let mut failures = Vec::new();
                     if !to_rerun.is_empty() {
-                        let _diagnostics = match run_tasks_parallel(
+                        let cancel = CancelToken::new();
+                        if let Ok(mut token) = building.lock() {
+                            *token = cancel.clone();
+                        }
+
+                        let result = run_tasks_parallel(
                             site,
                             &globals,
                             &mut cache,
                             &to_rerun,
                             &dirty_nodes,
-                        ) {
-                            Ok(res) => res,
+                            &cancel,
+                        );
+
+                        // Some tasks may have finished before the rebuild was
+                        // cancelled, everything affected runs again next time.
+                        if cancel.is_cancelled() {
+                            tracing::info!("change detected during rebuild, restarting...");
+                            pending_dirty = to_rerun;
+                            pending_static = static_dirty;
+                            continue;
+                        }
+
+                        match result {
+                            Ok(diagnostics) => {
+                                failures = diagnostics.failures;
+                                failed = diagnostics.failed;
+                            }
                             Err(e) => {
+                                // Which tasks didn't finish is unknown, all of
+                                // them run again with the next change.
                                 tracing::error!("Error running tasks: {}", e);
+                                failed = to_rerun;
+                                reload.send(&ReloadMessage::failed([&e]));
                                 continue;
                             }
                         };
                     }
 
                     if static_dirty {
-                        static_files = match crate::utils::collect_static(&copied, out_dir) {
-                            Ok(files) => files,
-                            Err(e) => {
-                                tracing::error!("failed to collect static files: {}", e);
-                                continue;
-                            }
-                        };
+                        static_files =
+                            match crate::utils::collect_static(&copied, out_dir, &*site.vfs) {
+                                Ok(files) => files,
+                                Err(e) => {
+                                    report_failure(&reload, BuildError::Other(e.into()));
+                                    continue;
+                                }
+                            };
                     }
 
                     let mut snapshot = match collect_manifest(&cache, &site.graph) {
                         Ok(snapshot) => snapshot,
                         Err(e) => {
-                            tracing::error!("failed to collect output manifest: {}", e);
+                            report_failure(&reload, e);
                             continue;
                         }
                     };
-                    let mut static_manifest_ok = true;
-                    for entry in &static_files {
-                        if let Err(e) = snapshot
-                            .insert_static_file(entry.dist_rel.clone(), entry.source_utf8.clone())
-                        {
-                            tracing::error!("failed to add static file to manifest: {}", e);
-                            static_manifest_ok = false;
-                            break;
-                        }
-                    }
-                    if !static_manifest_ok {
+                    let inserted = static_files.iter().try_for_each(|entry| {
+                        snapshot.insert_static_file(entry.dist_rel.clone(), entry.source.clone())
+                    });
+                    if let Err(e) = inserted {
+                        report_failure(&reload, e);
                         continue;
                     }
-                    if let Err(e) = crate::utils::copy_static_entries(&static_files, &site.progress.copy) {
-                        tracing::error!("failed to copy static files: {}", e);
+                    if let Err(e) = crate::utils::copy_static_entries(
+                        &static_files,
+                        &site.progress.copy,
+                        &*site.vfs,
+                    ) {
+                        report_failure(&reload, BuildError::Other(e.into()));
                         continue;
                     }
                     tracing::info!("collected {} pages", snapshot.page_count());
-                    if let Err(e) = snapshot.commit_diff(&prev_snapshot, out_dir) {
-                        tracing::error!("failed to write pages to dist: {}", e);
+                    if let Err(e) = site
+                        .hooks
+                        .after_tasks(&globals, &SnapshotView::new(&snapshot))
+                    {
+                        report_failure(&reload, e);
                         continue;
                     }
+                    let report = match snapshot.commit_diff(&prev_snapshot, out_dir) {
+                        Ok(report) => report,
+                        Err(e) => {
+                            report_failure(&reload, BuildError::Io(e));
+                            continue;
+                        }
+                    };
+                    tracing::info!("dist: {}", report);
                     if let Err(e) = snapshot.to_meta().save(cache_dir) {
                         tracing::warn!("failed to save snapshot meta: {}", e);
                     }
+                    if let Err(e) = site.stats.save(cache_dir) {
+                        tracing::warn!("failed to save stat cache: {}", e);
+                    }
+                    if let Err(e) = site.timings.save(cache_dir) {
+                        tracing::warn!("failed to save task timings: {}", e);
+                    }
                     prev_snapshot = snapshot;
-                    tx_reload.send(()).ok();
+                    if let Err(e) = site.hooks.after_commit(&globals, &report) {
+                        tracing::error!("{}", e);
+                        failures.push(e);
+                    }
+                    // Stylesheets only changed if nothing but stylesheet
+                    // sources did, the pages then only got new hrefs.
+                    let css = !dirty_nodes.is_empty()
+                        && dirty_nodes
+                            .iter()
+                            .all(|index| site.stylesheets.contains(index));
+                    let message = match static_dirty {
+                        true => ReloadMessage::all(),
+                        false => ReloadMessage::changed(&report, css),
+                    };
+                    reload.send(&message);
+                    // Pages built despite the failures are reloaded first.
+                    if !failures.is_empty() {
+                        reload.send(&ReloadMessage::failed(&failures));
+                    }
                     tracing::info!("rebuild complete, watching for changes...");
                 }
             }
@@ -233,83 +333,32 @@ pub fn watch<G: Send + Sync>(
     }
 }
 
-fn reserve_port() -> std::io::Result<(TcpListener, u16)> {
-    let listener = match TcpListener::bind("127.0.0.1:1337") {
-        Ok(sock) => sock,
-        Err(_) => TcpListener::bind("127.0.0.1:0")?,
-    };
-
-    let addr = listener.local_addr()?;
-    let port = addr.port();
-    Ok((listener, port))
+/// Logs an error which stopped a rebuild and shows it on the connected pages.
+fn report_failure(reload: &Reload, error: BuildError) {
+    tracing::error!("{}", error);
+    reload.send(&ReloadMessage::failed([&error]));
 }
 
-fn new_thread_ws_incoming(
-    server: TcpListener,
-    client: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
-) -> JoinHandle<()> {
-    std::thread::spawn(move || {
-        for stream in server.incoming() {
-            let stream = match stream {
-                Ok(s) => s,
-                Err(e) => {
-                    tracing::warn!("WebSocket: incoming stream error: {e}");
-                    continue;
-                }
-            };
-            let socket = match tungstenite::accept(stream) {
-                Ok(s) => s,
-                Err(e) => {
-                    tracing::warn!("WebSocket: handshake failed: {e}");
-                    continue;
-                }
-            };
-            #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
-            client.lock().unwrap().push(socket);
-        }
-    })
+/// Starts the HTTP server, which serves both `dist` and the live-reload
+/// endpoint, returns the port pages connect to.
+#[cfg(feature = "server")]
+fn start_live_reload<G: Send + Sync>(
+    site: &Website<G>,
+    out_dir: &Utf8Path,
+) -> anyhow::Result<(Reload, u16)> {
+    let reload = Reload::new();
+    let (port, _thread) = super::http::start(&site.server, out_dir.to_owned(), reload.clone())?;
+    Ok((reload, port))
 }
 
-fn new_thread_ws_reload(
-    client: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
-) -> (Sender<()>, JoinHandle<()>) {
-    let (tx, rx) = std::sync::mpsc::channel();
-
-    let thread = std::thread::spawn(move || {
-        while rx.recv().is_ok() {
-            #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
-            let mut clients = client.lock().unwrap();
-            let mut broken = vec![];
-
-            for (i, socket) in clients.iter_mut().enumerate() {
-                match socket.send("reload".into()) {
-                    Ok(_) => {}
-                    Err(tungstenite::error::Error::Io(e)) => {
-                        if e.kind() == std::io::ErrorKind::BrokenPipe {
-                            broken.push(i);
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Error: {e:?}");
-                    }
-                }
-            }
-
-            for i in broken.into_iter().rev() {
-                clients.remove(i);
-            }
-
-            // Close all but the last 10 connections
-            let len = clients.len();
-            if len > 10 {
-                for mut socket in clients.drain(0..len - 10) {
-                    socket.close(None).ok();
-                }
-            }
-        }
-    });
-
-    (tx, thread)
+/// Starts the WebSocket server, `dist` isn't served without the `server`
+/// feature.
+#[cfg(not(feature = "server"))]
+fn start_live_reload<G: Send + Sync>(
+    site: &Website<G>,
+    _: &Utf8Path,
+) -> anyhow::Result<(Reload, u16)> {
+    Ok(super::ws::start(&site.server)?)
 }
 
 /// Splits a glob string into a canonicalized static root path (for
@@ -0,0 +1,133 @@
+//! Live reload without the `server` feature, a WebSocket server listening
+//! on its own port, separate from the site.
+
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use tungstenite::WebSocket;
+
+use crate::ServerConfig;
+use crate::server::ReloadMessage;
+
+/// Sends messages to every page connected to the WebSocket server.
+pub struct Reload {
+    sender: Sender<String>,
+    /// Errors of the last rebuild, also sent to pages connecting later.
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl Reload {
+    pub fn send(&self, message: &ReloadMessage) {
+        let json = message.to_json();
+        if let Ok(mut error) = self.error.lock() {
+            *error = matches!(message, ReloadMessage::Error { .. }).then(|| json.clone());
+        }
+        self.sender.send(json).ok();
+    }
+}
+
+/// Starts the WebSocket server, returns the port it listens on.
+pub fn start(config: &ServerConfig) -> std::io::Result<(Reload, u16)> {
+    let (tcp, port) = reserve_port(config)?;
+    let clients = Arc::new(Mutex::new(vec![]));
+    let error = Arc::new(Mutex::new(None));
+
+    let _thread_i = new_thread_ws_incoming(tcp, clients.clone(), error.clone());
+    let (sender, _thread_o) = new_thread_ws_reload(clients);
+
+    Ok((Reload { sender, error }, port))
+}
+
+/// Binds the WebSocket server, falling back to any free port when the
+/// default one is taken and no port was configured.
+fn reserve_port(config: &ServerConfig) -> std::io::Result<(TcpListener, u16)> {
+    let listener = match config.live_reload_port {
+        Some(port) => TcpListener::bind((config.host, port))?,
+        None => match TcpListener::bind((config.host, 1337)) {
+            Ok(sock) => sock,
+            Err(_) => TcpListener::bind((config.host, 0))?,
+        },
+    };
+
+    let addr = listener.local_addr()?;
+    let port = addr.port();
+    Ok((listener, port))
+}
+
+fn new_thread_ws_incoming(
+    server: TcpListener,
+    client: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
+    error: Arc<Mutex<Option<String>>>,
+) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        for stream in server.incoming() {
+            let stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    tracing::warn!("WebSocket: incoming stream error: {e}");
+                    continue;
+                }
+            };
+            let mut socket = match tungstenite::accept(stream) {
+                Ok(s) => s,
+                Err(e) => {
+                    tracing::warn!("WebSocket: handshake failed: {e}");
+                    continue;
+                }
+            };
+            // Holding the lock until the socket is added keeps the reload
+            // thread from sending a message in between.
+            #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+            let mut clients = client.lock().unwrap();
+            #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+            if let Some(error) = error.lock().unwrap().clone() {
+                socket.send(error.into()).ok();
+            }
+            clients.push(socket);
+        }
+    })
+}
+
+fn new_thread_ws_reload(
+    client: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
+) -> (Sender<String>, JoinHandle<()>) {
+    let (tx, rx) = std::sync::mpsc::channel::<String>();
+
+    let thread = std::thread::spawn(move || {
+        while let Ok(message) = rx.recv() {
+            #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+            let mut clients = client.lock().unwrap();
+            let mut broken = vec![];
+
+            for (i, socket) in clients.iter_mut().enumerate() {
+                match socket.send(message.as_str().into()) {
+                    Ok(_) => {}
+                    Err(tungstenite::error::Error::Io(e)) => {
+                        if e.kind() == std::io::ErrorKind::BrokenPipe {
+                            broken.push(i);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Error: {e:?}");
+                    }
+                }
+            }
+
+            for i in broken.into_iter().rev() {
+                clients.remove(i);
+            }
+
+            // Close all but the last 10 connections
+            let len = clients.len();
+            if len > 10 {
+                for mut socket in clients.drain(0..len - 10) {
+                    socket.close(None).ok();
+                }
+            }
+        }
+    });
+
+    (tx, thread)
+}
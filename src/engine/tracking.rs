@@ -2,24 +2,49 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use glob::Pattern;
+use serde::{Deserialize, Serialize};
 
 use crate::core::ArcStr;
 use crate::engine::{Map, Provenance};
 use crate::error::HauchiwaError;
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct IterationState {
     pub count: usize,
     pub exhausted: bool,
 }
 
-#[derive(Clone, Default, Debug)]
+impl IterationState {
+    fn merge(&mut self, other: &IterationState) {
+        self.count = self.count.max(other.count);
+        self.exhausted |= other.exhausted;
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct TrackerState {
     pub accessed: HashMap<ArcStr, Provenance>,
     pub globs: HashMap<String, IterationState>,
     pub iterated: IterationState,
 }
 
+impl TrackerState {
+    /// Combines the accesses recorded by two separate executions, the result
+    /// is invalidated by any change that would invalidate either of them.
+    pub(crate) fn merge(&mut self, other: &TrackerState) {
+        self.accessed.extend(
+            other
+                .accessed
+                .iter()
+                .map(|(key, provenance)| (key.clone(), *provenance)),
+        );
+        for (pattern, state) in &other.globs {
+            self.globs.entry(pattern.clone()).or_default().merge(state);
+        }
+        self.iterated.merge(&other.iterated);
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct TrackerPtr {
     pub(crate) ptr: Arc<Mutex<TrackerState>>,
@@ -31,6 +56,21 @@ pub struct Tracking {
 }
 
 impl Tracking {
+    /// Wraps already collected states, e.g. when a task combines the tracking
+    /// of several executions of its callback.
+    pub(crate) fn from_states(states: Vec<Option<TrackerState>>) -> Self {
+        Self {
+            edges: states
+                .into_iter()
+                .map(|state| {
+                    state.map(|state| TrackerPtr {
+                        ptr: Arc::new(Mutex::new(state)),
+                    })
+                })
+                .collect(),
+        }
+    }
+
     pub(crate) fn unwrap(self) -> Vec<Option<TrackerState>> {
         self.edges
             .into_iter()
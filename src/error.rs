@@ -3,6 +3,7 @@ use std::sync::Arc;
 use std::sync::mpsc::{RecvError, SendError};
 
 pub use anyhow::Error as RuntimeError;
+use camino::{Utf8Path, Utf8PathBuf};
 use thiserror::Error;
 
 #[derive(Debug, Error, Clone)]
@@ -76,6 +77,19 @@ pub enum LoaderError {
 
     #[error("An error occured while loading asset.\n{0}")]
     Userland(#[from] anyhow::Error),
+
+    #[error("Couldn't load file '{0}'.\n{1}")]
+    File(Utf8PathBuf, anyhow::Error),
+}
+
+impl LoaderError {
+    /// Returns the path of the source file that failed to load, if known.
+    pub fn path(&self) -> Option<&Utf8Path> {
+        match self {
+            LoaderError::File(path, _) => Some(path),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -96,6 +110,12 @@ pub enum BuildError {
     #[error("Task '{0}':\n{1}")]
     Task(String, anyhow::Error),
 
+    #[error("Task '{0}' timed out after {1:?}")]
+    Timeout(String, std::time::Duration),
+
+    #[error("{} task(s) failed:\n{}", .0.len(), list_errors(.0))]
+    Tasks(Vec<BuildError>),
+
     #[error("Hook:\n{0}")]
     Hook(anyhow::Error),
 
@@ -103,6 +123,14 @@ pub enum BuildError {
     Other(anyhow::Error),
 }
 
+fn list_errors(errors: &[BuildError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
 #[cfg(feature = "live")]
 #[derive(Debug, Error)]
 pub enum WatchError {
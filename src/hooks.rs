@@ -0,0 +1,139 @@
+//! User callbacks invoked at fixed points of every build.
+//!
+//! Hooks are registered on the [`Blueprint`](crate::Blueprint) and run both
+//! by [`Website::build`](crate::Website::build) and by every rebuild in
+//! watch mode. An error returned from a hook is reported as
+//! [`BuildError::Hook`].
+
+use crate::Environment;
+use crate::error::BuildError;
+use crate::snapshot::{CommitReport, SnapshotView};
+
+type BeforeBuild<G> = Box<dyn Fn(&Environment<G>) -> anyhow::Result<()> + Send + Sync>;
+type AfterTasks<G> =
+    Box<dyn Fn(&Environment<G>, &SnapshotView<'_>) -> anyhow::Result<()> + Send + Sync>;
+type AfterCommit<G> =
+    Box<dyn Fn(&Environment<G>, &CommitReport) -> anyhow::Result<()> + Send + Sync>;
+
+/// The hooks registered for a website, run in the order they were added.
+pub(crate) struct Hooks<G: Send + Sync> {
+    pub before_build: Vec<BeforeBuild<G>>,
+    pub after_tasks: Vec<AfterTasks<G>>,
+    pub after_commit: Vec<AfterCommit<G>>,
+}
+
+impl<G: Send + Sync> Hooks<G> {
+    /// Runs before any task is executed.
+    pub(crate) fn before_build(&self, env: &Environment<G>) -> Result<(), BuildError> {
+        for hook in &self.before_build {
+            hook(env).map_err(BuildError::Hook)?;
+        }
+        Ok(())
+    }
+
+    /// Runs once every task finished, before anything is written to `dist`.
+    pub(crate) fn after_tasks(
+        &self,
+        env: &Environment<G>,
+        snapshot: &SnapshotView<'_>,
+    ) -> Result<(), BuildError> {
+        for hook in &self.after_tasks {
+            hook(env, snapshot).map_err(BuildError::Hook)?;
+        }
+        Ok(())
+    }
+
+    /// Runs after the snapshot was committed to `dist`.
+    pub(crate) fn after_commit(
+        &self,
+        env: &Environment<G>,
+        report: &CommitReport,
+    ) -> Result<(), BuildError> {
+        for hook in &self.after_commit {
+            hook(env, report).map_err(BuildError::Hook)?;
+        }
+        Ok(())
+    }
+}
+
+impl<G: Send + Sync> Default for Hooks<G> {
+    fn default() -> Self {
+        Self {
+            before_build: Vec::new(),
+            after_tasks: Vec::new(),
+            after_commit: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::error::HauchiwaError;
+    use crate::test_utils::TempDir;
+    use crate::{Blueprint, Output, Website};
+
+    fn build(site: &mut Website) -> Result<(), HauchiwaError> {
+        site.build(()).map(|_| ())
+    }
+
+    fn blueprint(name: &str) -> (Blueprint, TempDir) {
+        let root = TempDir::new(name);
+
+        let mut config = Blueprint::<()>::new()
+            .set_dir_dist(root.join("dist"))
+            .set_dir_cache(root.join("cache"));
+        config
+            .task()
+            .run(|_| Ok(Output::html("index", "<h1>Hello</h1>")));
+
+        (config, root)
+    }
+
+    #[test]
+    fn test_hooks_run_in_order() {
+        let (mut config, _root) = blueprint("hooks");
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let log = events.clone();
+        config.on_before_build(move |_| {
+            log.lock().unwrap().push(String::from("before"));
+            Ok(())
+        });
+        let log = events.clone();
+        config.on_after_tasks(move |_, snapshot| {
+            let page = snapshot.page("index.html").is_some();
+            log.lock().unwrap().push(format!("tasks {page}"));
+            Ok(())
+        });
+        let log = events.clone();
+        config.on_after_commit(move |_, report| {
+            let written: Vec<_> = report.written().map(|file| file.path.as_str()).collect();
+            log.lock().unwrap().push(format!("commit {written:?}"));
+            Ok(())
+        });
+
+        build(&mut config.finish()).unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            ["before", "tasks true", r#"commit ["index.html"]"#]
+        );
+    }
+
+    #[test]
+    fn test_hook_error_aborts_commit() {
+        let (mut config, root) = blueprint("hooks-error");
+        config.on_after_tasks(|_, _| anyhow::bail!("nope"));
+
+        let result = build(&mut config.finish());
+
+        assert!(matches!(
+            result,
+            Err(HauchiwaError::Build(crate::error::BuildError::Hook(_)))
+        ));
+        assert!(!root.join("dist/index.html").exists());
+    }
+}
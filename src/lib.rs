@@ -1,17 +1,30 @@
 #![doc = include_str!("../README.md")]
 #![deny(unsafe_code, clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 
+// Lets code generated by `hauchiwa-macros` refer to this crate in its own tests.
+#[cfg(all(test, feature = "macros"))]
+extern crate self as hauchiwa;
+
 mod blueprint;
 mod core;
 mod engine;
 pub mod error;
+mod hooks;
 pub mod loader;
 #[cfg(feature = "logging")]
 mod logging;
 pub mod output;
 pub mod preflight;
+mod server;
 pub(crate) mod snapshot;
+mod stat;
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test_utils;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 mod utils;
+pub mod vfs;
 
 pub use camino;
 pub use gitscan as git;
@@ -23,15 +36,30 @@ pub mod minijinja {
 
 pub use crate::blueprint::{Blueprint, Website};
 pub use crate::core::{Environment, FileMetadata, ImportMap, Mode, Store, TaskContext};
-pub use crate::engine::{Diagnostics, Many, One, Tracker};
-pub use crate::output::Output;
+pub use crate::engine::{
+    CancelToken, Cancelled, Dependencies, Diagnostics, Many, One, Page, Tracker,
+};
+pub use crate::output::{Output, OutputFilter};
 pub use crate::preflight::Requirement;
+pub use crate::server::ServerConfig;
+pub use crate::snapshot::{BuiltFile, BuiltSite, CommitReport, DistFile, Producer, SnapshotView};
 pub use crate::utils::ProgressStyles;
 
+#[cfg(feature = "macros")]
+pub use hauchiwa_macros::{Dependencies, embed_dir};
+
+// Used by code generated in `hauchiwa-macros`, not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::core::Dynamic;
+    pub use crate::engine::{Dependencies, TrackerState, Tracking};
+    pub use petgraph::graph::NodeIndex;
+}
+
 pub mod prelude {
     pub use super::blueprint::{Blueprint, Website};
     pub use super::core::{ImportMap, Store, TaskContext};
-    pub use super::engine::{Diagnostics, Many, One, Tracker};
+    pub use super::engine::{Diagnostics, Many, One, Page, Tracker};
     pub use super::output::Output;
 }
 
@@ -45,20 +45,26 @@ pub mod pagefind;
 #[cfg(feature = "sitemap")]
 pub mod sitemap;
 
-use std::collections::{BTreeMap, HashSet};
-use std::fs;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hasher;
+use std::sync::{Arc, Mutex};
 
 use camino::{Utf8Path, Utf8PathBuf};
-use glob::{Pattern, glob};
+use glob::Pattern;
 use gray_matter::engine::YAML;
 use petgraph::graph::NodeIndex;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
 
-use crate::core::{Dynamic, Hash32, Store, TaskContext};
+use crate::core::{ArcStr, Blake3Hasher, Dynamic, Hash32, ImportMap, Store, TaskContext};
 use crate::engine::{Map, Provenance, Tracking, TypedFine};
+use crate::error::LoaderError;
+use crate::stat::StatCache;
+use crate::vfs::Vfs;
 
 /// Represents a compiled JavaScript module.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Script {
     /// The path to the compiled JavaScript file (e.g., hashed path).
     pub path: Utf8PathBuf,
@@ -72,13 +78,67 @@ pub struct Input {
     pub path: Utf8PathBuf,
     /// The hash of the file content.
     pub(crate) hash: Hash32,
+    /// The filesystem the file was found in.
+    pub(crate) vfs: Arc<dyn Vfs>,
 }
 
 impl Input {
-    /// Reads the file content from the filesystem.
+    /// Reads the file content from the [`Vfs`] of the website.
     pub fn read(&self) -> std::io::Result<Box<[u8]>> {
-        fs::read(&self.path).map(Into::into)
+        self.vfs.read(&self.path)
+    }
+}
+
+/// Hashes every file matched by the entry and watch globs, used to detect
+/// changes to the inputs of a loader between separate runs.
+pub(crate) fn fingerprint_globs(
+    vfs: &dyn Vfs,
+    stats: &StatCache,
+    glob_entry: &[String],
+    glob_watch: &[Pattern],
+) -> anyhow::Result<Hash32> {
+    let mut paths = BTreeSet::new();
+    for glob_entry in glob_entry
+        .iter()
+        .map(String::as_str)
+        .chain(glob_watch.iter().map(Pattern::as_str))
+    {
+        paths.extend(vfs.glob(glob_entry)?);
+    }
+
+    let hashes: std::io::Result<Vec<_>> = paths
+        .into_par_iter()
+        .map(|path| stats.hash_file(vfs, &path).map(|hash| (path, hash)))
+        .collect();
+
+    let mut hasher = Blake3Hasher::default();
+    for (path, hash) in hashes? {
+        hasher.write(path.as_str().as_bytes());
+        hasher.write(&hash.to_bytes());
+    }
+
+    Ok(hasher.into())
+}
+
+/// Splits the per-file results of a loader. Failed files are either reported
+/// through the store and left out of the output, or fail the whole task.
+fn isolate_failures<G, T>(
+    context: &TaskContext<G>,
+    runtime: &Store,
+    results: Vec<Result<T, LoaderError>>,
+) -> Result<Vec<T>, LoaderError>
+where
+    G: Send + Sync,
+{
+    let mut loaded = Vec::with_capacity(results.len());
+    for result in results {
+        match result {
+            Ok(item) => loaded.push(item),
+            Err(e) if context.continue_on_error => runtime.report(e),
+            Err(e) => return Err(e),
+        }
     }
+    Ok(loaded)
 }
 
 type GlobFilesCallback<G, R> = Box<
@@ -92,17 +152,32 @@ type GlobFilesCallback<G, R> = Box<
 pub(crate) struct GlobFiles<G, R>
 where
     G: Send + Sync + 'static,
-    R: Send + Sync + 'static,
+    R: Send + Sync + Clone + 'static,
 {
+    name: Option<Cow<'static, str>>,
     glob_entry: Vec<String>,
     glob_watch: Vec<Pattern>,
     callback: GlobFilesCallback<G, R>,
+    /// Per-file side effects of the previous run, needed to reuse old items.
+    /// An entry is only used while the cached output still holds an item
+    /// with the same content hash, so entries outliving that output, e.g.
+    /// after a restore from the persistent cache, are never reused.
+    previous: Mutex<HashMap<Utf8PathBuf, GlobFilesEntry>>,
+}
+
+/// What a single file contributed to the output during the previous run.
+#[derive(Clone)]
+struct GlobFilesEntry {
+    key: ArcStr,
+    hash: Hash32,
+    imports: ImportMap,
+    store_paths: Vec<Utf8PathBuf>,
 }
 
 impl<G, R> GlobFiles<G, R>
 where
     G: Send + Sync + 'static,
-    R: Send + Sync + 'static,
+    R: Send + Sync + Clone + 'static,
 {
     /// Creates a new `GlobFiles` task with pre-compiled watch patterns.
     ///
@@ -115,22 +190,81 @@ where
             + 'static,
     {
         Self {
+            name: None,
             glob_entry,
             glob_watch,
             callback: Box::new(callback),
+            previous: Mutex::default(),
         }
     }
+
+    /// Overrides the name of the task, which defaults to its patterns.
+    pub fn with_name(mut self, name: Option<Cow<'static, str>>) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Loads a single file, reusing the result of the previous run if the file
+    /// content didn't change.
+    fn load_file(
+        &self,
+        context: &TaskContext<G>,
+        runtime: &Store,
+        old_map: Option<&Map<R>>,
+        previous: &HashMap<Utf8PathBuf, GlobFilesEntry>,
+        path: Utf8PathBuf,
+    ) -> anyhow::Result<(Utf8PathBuf, R, GlobFilesEntry)> {
+        context.cancel.check()?;
+        let hash = runtime.stats.hash_file(&*runtime.vfs, &path)?;
+
+        // If the file content didn't change, reuse the old item along with
+        // everything it saved to the store.
+        if let Some(old_map) = old_map
+            && let Some(entry) = previous.get(&path)
+            && entry.hash == hash
+            && let Some((old_item, old_provenance)) = old_map.map.get(&entry.key)
+            && old_provenance.0 == hash
+        {
+            return Ok((path, old_item.clone(), entry.clone()));
+        }
+
+        let file = Input {
+            path: path.clone(),
+            hash,
+            vfs: runtime.vfs.clone(),
+        };
+
+        let mut rt = runtime.fork();
+
+        // call the user callback, items run on rayon threads which don't
+        // know the current task otherwise
+        let (key, res) = context
+            .cancel
+            .enter(|| (self.callback)(context, &mut rt, file))?;
+
+        let entry = GlobFilesEntry {
+            key: key.as_str().into(),
+            hash,
+            imports: rt.imports,
+            store_paths: rt.store_paths,
+        };
+
+        Ok((path, res, entry))
+    }
 }
 
 impl<G, R> TypedFine<G> for GlobFiles<G, R>
 where
     G: Send + Sync + 'static,
-    R: Send + Sync + 'static,
+    R: Send + Sync + Clone + 'static,
 {
     type Output = R;
 
     fn get_name(&self) -> String {
-        self.glob_entry.join(", ")
+        match &self.name {
+            Some(name) => name.to_string(),
+            None => self.glob_entry.join(", "),
+        }
     }
 
     fn dependencies(&self) -> Vec<NodeIndex> {
@@ -149,52 +283,57 @@ where
         context: &TaskContext<G>,
         runtime: &mut Store,
         _: &[Dynamic],
-        _: Option<&Dynamic>,
+        old_output: Option<&Dynamic>,
         _: &HashSet<NodeIndex>,
     ) -> anyhow::Result<(Tracking, Map<Self::Output>)> {
         let mut paths = Vec::new();
         for glob_entry in &self.glob_entry {
-            for path in glob(glob_entry)? {
-                // Handle glob errors immediately here
-                paths.push(Utf8PathBuf::try_from(path?)?);
-            }
+            paths.extend(runtime.vfs.glob(glob_entry)?);
         }
 
         // we can override the style to have progress
         context.span.pb_set_style(&context.progress.task_items);
         context.span.pb_set_length(paths.len() as u64);
 
-        let results: anyhow::Result<Vec<_>> = paths
+        let old_map = old_output.and_then(|d| d.downcast_ref::<Map<Self::Output>>());
+        #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+        let mut previous = self.previous.lock().unwrap();
+
+        let results = paths
             .into_par_iter()
             .map(|path| {
-                let hash = Hash32::hash_file(&path)?;
-                let file = Input { path, hash };
-
-                let mut rt = runtime.fork();
-
-                // call the user callback
-                let (path, res) = (self.callback)(context, &mut rt, file)?;
+                let result = self.load_file(context, runtime, old_map, &previous, path.clone());
 
                 // next iteration
                 context.span.pb_inc(1);
 
-                Ok((Provenance(hash), path, res, rt.imports, rt.store_paths))
+                result.map_err(|e| LoaderError::File(path, e))
             })
             .collect();
 
         let mut map = BTreeMap::new();
-        for (provenance, path, res, imports, store_paths) in results? {
-            map.insert(path.as_str().into(), (res, provenance));
-            runtime.imports.merge(imports);
-            runtime.store_paths.extend(store_paths);
+        let mut next = HashMap::new();
+        for (path, res, entry) in isolate_failures(context, runtime, results)? {
+            map.insert(entry.key.clone(), (res, Provenance(entry.hash)));
+            runtime.imports.merge(entry.imports.clone());
+            runtime
+                .store_paths
+                .extend(entry.store_paths.iter().cloned());
+            next.insert(path, entry);
         }
 
+        *previous = next;
+
         Ok((Tracking::default(), Map { map, dirty: false }))
     }
 
     fn is_dirty(&self, path: &Utf8Path) -> bool {
         self.glob_watch.iter().any(|p| p.matches(path.as_str()))
     }
+
+    fn fingerprint(&self, vfs: &dyn Vfs, stats: &StatCache) -> anyhow::Result<Option<Hash32>> {
+        fingerprint_globs(vfs, stats, &self.glob_entry, &self.glob_watch).map(Some)
+    }
 }
 
 type GlobBundleCallback<G, R> = Box<
@@ -203,6 +342,8 @@ type GlobBundleCallback<G, R> = Box<
         + Sync,
 >;
 
+type GlobBundleItem<R> = (Provenance, Utf8PathBuf, R, ImportMap, Vec<Utf8PathBuf>);
+
 type PreRunCallback<G> =
     Box<dyn Fn(&TaskContext<G>, &mut Store) -> anyhow::Result<()> + Send + Sync>;
 
@@ -260,6 +401,32 @@ where
         self.requirements.push(req);
         self
     }
+
+    /// Loads a single entry file.
+    fn load_file(
+        &self,
+        context: &TaskContext<G>,
+        runtime: &Store,
+        path: Utf8PathBuf,
+    ) -> anyhow::Result<GlobBundleItem<R>> {
+        context.cancel.check()?;
+        let hash = runtime.stats.hash_file(&*runtime.vfs, &path)?;
+        let file = Input {
+            path,
+            hash,
+            vfs: runtime.vfs.clone(),
+        };
+
+        let mut rt = runtime.fork();
+
+        // call the user callback, items run on rayon threads which don't
+        // know the current task otherwise
+        let (hash, path, res) = context
+            .cancel
+            .enter(|| (self.callback)(context, &mut rt, file))?;
+
+        Ok((Provenance(hash), path, res, rt.imports, rt.store_paths))
+    }
 }
 
 impl<G, R> TypedFine<G> for GlobBundle<G, R>
@@ -298,36 +465,29 @@ where
 
         let mut paths = Vec::new();
         for glob_entry in &self.glob_entry {
-            for path in glob(glob_entry)? {
-                // Handle glob errors immediately here
-                paths.push(Utf8PathBuf::try_from(path?)?);
-            }
+            paths.extend(runtime.vfs.glob(glob_entry)?);
         }
 
         // we can override the style to have progress
         context.span.pb_set_style(&context.progress.task_items);
         context.span.pb_set_length(paths.len() as u64);
 
-        let results: anyhow::Result<Vec<_>> = paths
+        let results = paths
             .into_par_iter()
             .map(|path| {
-                let hash = Hash32::hash_file(&path)?;
-                let file = Input { path, hash };
-
-                let mut rt = runtime.fork();
-
-                // call the user callback
-                let (hash, path, res) = (self.callback)(context, &mut rt, file)?;
+                let result = self.load_file(context, runtime, path.clone());
 
                 // next iteration
                 context.span.pb_inc(1);
 
-                Ok((Provenance(hash), path, res, rt.imports, rt.store_paths))
+                result.map_err(|e| LoaderError::File(path, e))
             })
             .collect();
 
         let mut map = BTreeMap::new();
-        for (provenance, path, res, imports, store_paths) in results? {
+        for (provenance, path, res, imports, store_paths) in
+            isolate_failures(context, runtime, results)?
+        {
             map.insert(path.as_str().into(), (res, provenance));
             runtime.imports.merge(imports);
             runtime.store_paths.extend(store_paths);
@@ -340,6 +500,10 @@ where
         self.glob_watch.iter().any(|p| p.matches(path.as_str()))
     }
 
+    fn fingerprint(&self, vfs: &dyn Vfs, stats: &StatCache) -> anyhow::Result<Option<Hash32>> {
+        fingerprint_globs(vfs, stats, &self.glob_entry, &self.glob_watch).map(Some)
+    }
+
     fn requirements(&self) -> Vec<crate::preflight::Requirement> {
         self.requirements.clone()
     }
@@ -381,3 +545,95 @@ macro_rules! matter_parser {
 
 matter_parser!(parse_yaml, YAML);
 // matter_parser!(parse_json, JSON);
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::test_utils::{TempDir, make_ctx};
+
+    #[test]
+    fn test_glob_files_reuses_unchanged() -> anyhow::Result<()> {
+        let tmp = TempDir::new("glob");
+        let dir = tmp.path();
+        fs::write(dir.join("a.txt"), "a")?;
+        fs::write(dir.join("b.txt"), "b")?;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let task = GlobFiles::new(
+            vec![format!("{dir}/*.txt")],
+            vec![],
+            move |_: &TaskContext<()>, store: &mut Store, input: Input| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                store.store_paths.push(input.path.with_extension("out"));
+                Ok((input.path.clone(), String::from_utf8(input.read()?.into())?))
+            },
+        );
+
+        let ctx = make_ctx();
+        let mut store = Store::new();
+        let (_, output) = task.execute(&ctx, &mut store, &[], None, &HashSet::new())?;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        fs::write(dir.join("b.txt"), "changed")?;
+
+        let old: Dynamic = Arc::new(output);
+        let mut store = Store::new();
+        let (_, output) = task.execute(&ctx, &mut store, &[], Some(&old), &HashSet::new())?;
+
+        // only the changed file goes through the callback again
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(output.map[dir.join("a.txt").as_str()].0, "a");
+        assert_eq!(output.map[dir.join("b.txt").as_str()].0, "changed");
+        // side effects of the reused file are kept
+        assert_eq!(store.store_paths.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_glob_files_isolates_failures() -> anyhow::Result<()> {
+        let tmp = TempDir::new("isolate");
+        let dir = tmp.path();
+        fs::write(dir.join("good.txt"), "1")?;
+        fs::write(dir.join("bad.txt"), "x")?;
+
+        let task = GlobFiles::new(
+            vec![format!("{dir}/*.txt")],
+            vec![],
+            |_: &TaskContext<()>, _: &mut Store, input: Input| {
+                let text = String::from_utf8(input.read()?.into())?;
+                Ok((input.path.clone(), text.parse::<u32>()?))
+            },
+        );
+
+        // fails as a whole by default
+        let mut store = Store::new();
+        let result = task.execute(&make_ctx(), &mut store, &[], None, &HashSet::new());
+        let error = result.err().unwrap();
+        let error = error.downcast_ref::<LoaderError>().unwrap();
+        assert_eq!(error.path(), Some(dir.join("bad.txt").as_path()));
+
+        let ctx = TaskContext {
+            continue_on_error: true,
+            ..make_ctx()
+        };
+        let mut store = Store::new();
+        let (_, output) = task.execute(&ctx, &mut store, &[], None, &HashSet::new())?;
+
+        assert_eq!(output.map.len(), 1);
+        assert_eq!(output.map[dir.join("good.txt").as_str()].0, 1);
+
+        let failures = store.failures.lock().unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].path(), Some(dir.join("bad.txt").as_path()));
+
+        Ok(())
+    }
+}
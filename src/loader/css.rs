@@ -38,11 +38,16 @@
 //! }
 //! ```
 
+use std::borrow::Cow;
+use std::time::Duration;
+
 use glob::Pattern;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::blueprint::TaskOptions;
 use crate::core::Hash32;
-use crate::engine::Many;
+use crate::engine::{Limit, Many};
 use crate::loader::GlobBundle;
 use crate::{Blueprint, error::HauchiwaError};
 
@@ -63,7 +68,7 @@ pub enum StyleError {
 }
 
 /// Represents a compiled CSS file.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stylesheet {
     /// The path to the compiled CSS file.
     pub path: camino::Utf8PathBuf,
@@ -75,6 +80,7 @@ where
     G: Send + Sync,
 {
     blueprint: &'a mut Blueprint<G>,
+    options: TaskOptions,
     entry_globs: Vec<String>,
     entry_patterns: Vec<Pattern>,
     watch_globs: Vec<Pattern>,
@@ -88,6 +94,7 @@ where
     fn new(blueprint: &'a mut Blueprint<G>) -> Self {
         Self {
             blueprint,
+            options: TaskOptions::default(),
             entry_globs: Vec::new(),
             entry_patterns: Vec::new(),
             watch_globs: Vec::new(),
@@ -120,6 +127,21 @@ where
         self
     }
 
+    /// Assigns the loader to a resource class, at most `max` tasks of the
+    /// class run at the same time and the stylesheets are processed by at most
+    /// `max` threads.
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the loader if it runs longer than `timeout`, the remaining files
+    /// are not loaded once it elapses.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     /// Registers the task with the Blueprint.
     pub fn register(self) -> Many<Stylesheet> {
         let watch_globs = if self.watch_globs.is_empty() {
@@ -149,7 +171,11 @@ where
             Ok((hash, input.path, Stylesheet { path }))
         });
 
-        self.blueprint.add_task_fine(task)
+        let handle = self.blueprint.add_task_fine(task);
+        self.blueprint.set_options(handle.index(), self.options);
+        #[cfg(feature = "live")]
+        self.blueprint.stylesheets.insert(handle.index());
+        handle
     }
 }
 
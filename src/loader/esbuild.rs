@@ -33,15 +33,23 @@
 //!     Ok(app)
 //! }
 //! ```
+use std::borrow::Cow;
 use std::io::Write;
 use std::process::{Command, Stdio};
+use std::time::Duration;
 
 use camino::Utf8Path;
 use glob::Pattern;
 use thiserror::Error;
 
+use crate::blueprint::TaskOptions;
 use crate::core::Hash32;
-use crate::{Blueprint, engine::Many, error::HauchiwaError, loader::GlobBundle};
+use crate::{
+    Blueprint,
+    engine::{CancelToken, Limit, Many},
+    error::HauchiwaError,
+    loader::GlobBundle,
+};
 
 /// Errors that can occur when compiling JavaScript files.
 #[derive(Debug, Error)]
@@ -69,6 +77,7 @@ where
     G: Send + Sync,
 {
     blueprint: &'a mut Blueprint<G>,
+    options: TaskOptions,
     entry_globs: Vec<String>,
     entry_patterns: Vec<Pattern>,
     watch_globs: Vec<Pattern>,
@@ -84,6 +93,7 @@ where
     fn new(blueprint: &'a mut Blueprint<G>) -> Self {
         Self {
             blueprint,
+            options: TaskOptions::default(),
             entry_globs: Vec::new(),
             entry_patterns: Vec::new(),
             watch_globs: Vec::new(),
@@ -135,6 +145,21 @@ where
         self
     }
 
+    /// Assigns the loader to a resource class, at most `max` tasks of the
+    /// class run at the same time and the scripts are processed by at most
+    /// `max` threads.
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the loader if it runs longer than `timeout`, any child process
+    /// it is waiting for is killed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     /// Finalizes configuration and registers the task with the Blueprint.
     ///
     /// Returns a [`Many<Script>`] handle that resolves to one compiled output
@@ -151,16 +176,22 @@ where
         let externals = self.externals;
 
         let externals_clone = externals.clone();
-        let task = GlobBundle::new(self.entry_globs, watch_globs, move |_, store, input| {
-            let data = compile_esbuild(&input.path, bundle, minify, &externals_clone)?;
+        let task = GlobBundle::new(self.entry_globs, watch_globs, move |ctx, store, input| {
+            let data = compile_esbuild(
+                &input.path,
+                bundle,
+                minify,
+                &externals_clone,
+                ctx.cancel_token(),
+            )?;
             let hash = Hash32::hash(&data);
             let path = store.save(&data, "js").map_err(ScriptError::Build)?;
 
             Ok((hash, input.path, super::Script { path }))
         })
-        .pre_run(move |_, store| {
+        .pre_run(move |ctx, store| {
             for package in &externals {
-                let data = bundle_package(package, minify)?;
+                let data = bundle_package(package, minify, ctx.cancel_token())?;
                 let path = store.save(&data, "js").map_err(ScriptError::Build)?;
                 store.register(package.as_str(), path.as_str());
             }
@@ -168,7 +199,9 @@ where
         })
         .require(crate::preflight::Requirement::Binary("esbuild"));
 
-        self.blueprint.add_task_fine(task)
+        let handle = self.blueprint.add_task_fine(task);
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 }
 
@@ -200,6 +233,7 @@ fn compile_esbuild(
     bundle: bool,
     minify: bool,
     externals: &[String],
+    cancel: &CancelToken,
 ) -> Result<Vec<u8>, ScriptError> {
     let mut cmd = Command::new("esbuild");
     cmd.arg(file.as_str()).arg("--format=esm");
@@ -216,7 +250,8 @@ fn compile_esbuild(
         cmd.arg(format!("--external:{package}"));
     }
 
-    let output = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).output()?;
+    let child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let output = cancel.wait(child)?;
 
     if !output.status.success() {
         return Err(ScriptError::Esbuild(String::from_utf8(output.stderr)?));
@@ -225,7 +260,11 @@ fn compile_esbuild(
     Ok(output.stdout)
 }
 
-fn bundle_package(package: &str, minify: bool) -> Result<Vec<u8>, ScriptError> {
+fn bundle_package(
+    package: &str,
+    minify: bool,
+    cancel: &CancelToken,
+) -> Result<Vec<u8>, ScriptError> {
     let stdin_content = format!("export * from '{package}'");
 
     let mut cmd = Command::new("esbuild");
@@ -249,7 +288,7 @@ fn bundle_package(package: &str, minify: bool) -> Result<Vec<u8>, ScriptError> {
         .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "stdin was not piped"))?
         .write_all(stdin_content.as_bytes())?;
 
-    let output = child.wait_with_output()?;
+    let output = cancel.wait(child)?;
 
     if !output.status.success() {
         return Err(ScriptError::Esbuild(String::from_utf8(output.stderr)?));
@@ -1,29 +1,50 @@
+use std::borrow::Cow;
 use std::sync::Arc;
+use std::time::Duration;
 
 use camino::{Utf8Path, Utf8PathBuf};
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use glob::Pattern;
 
+use crate::blueprint::TaskOptions;
 use crate::{
     Blueprint, Output,
-    engine::Many,
+    engine::{Limit, Many},
     error::HauchiwaError,
     loader::{GlobFiles, Input},
     output::OutputBuilder,
 };
 
 /// Errors that can occur when loading files with frontmatter.
+///
+/// Each variant carries the path of the source file as its first field.
 #[derive(Debug, Error)]
 pub enum FrontmatterError {
+    /// Failed to read the content file.
+    #[error("IO error: {1}")]
+    Io(Utf8PathBuf, std::io::Error),
+
     /// Failed to convert file content to UTF-8.
-    #[error("UTF-8 conversion error: {0}")]
-    Utf8(#[from] std::str::Utf8Error),
+    #[error("UTF-8 conversion error: {1}")]
+    Utf8(Utf8PathBuf, std::str::Utf8Error),
 
     /// Failed to parse the frontmatter of the content file.
-    #[error("Frontmatter parsing error: {0}")]
-    Parse(anyhow::Error),
+    #[error("Frontmatter parsing error: {1}")]
+    Parse(Utf8PathBuf, anyhow::Error),
+}
+
+impl FrontmatterError {
+    /// Returns the path of the content file that failed to load.
+    pub fn path(&self) -> &Utf8Path {
+        match self {
+            FrontmatterError::Io(path, _)
+            | FrontmatterError::Utf8(path, _)
+            | FrontmatterError::Parse(path, _) => path,
+        }
+    }
 }
 
 /// This is the standard output of the [`Blueprint::load_documents`] loader.
@@ -31,7 +52,7 @@ pub enum FrontmatterError {
 /// # Generics
 ///
 /// * `T`: The type of the metadata (frontmatter), typically a struct deriving `Deserialize`.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Document<T> {
     /// The parsed frontmatter.
     pub matter: Box<T>,
@@ -41,7 +62,7 @@ pub struct Document<T> {
     pub meta: DocumentMeta,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentMeta {
     /// The original path of content file.
     pub path: Utf8PathBuf,
@@ -126,9 +147,10 @@ impl<T> Document<T> {
 pub struct DocumentLoader<'a, G, R>
 where
     G: Send + Sync,
-    R: DeserializeOwned + Send + Sync + 'static,
+    R: DeserializeOwned + Clone + Send + Sync + 'static,
 {
     blueprint: &'a mut Blueprint<G>,
+    options: TaskOptions,
     entry: Vec<String>,
     watch: Vec<Pattern>,
     base: Option<String>,
@@ -138,11 +160,12 @@ where
 impl<'a, G, R> DocumentLoader<'a, G, R>
 where
     G: Send + Sync + 'static,
-    R: DeserializeOwned + Send + Sync + 'static,
+    R: DeserializeOwned + Clone + Send + Sync + 'static,
 {
     fn new(blueprint: &'a mut Blueprint<G>) -> Self {
         Self {
             blueprint,
+            options: TaskOptions::default(),
             entry: Vec::new(),
             watch: Vec::new(),
             base: None,
@@ -175,7 +198,7 @@ where
     ///
     /// ```rust,no_run
     /// # use hauchiwa::Blueprint;
-    /// # #[derive(serde::Deserialize)]
+    /// # #[derive(serde::Deserialize, Clone)]
     /// # struct Page {}
     /// # fn main() -> Result<(), hauchiwa::error::HauchiwaError> {
     /// let mut site = Blueprint::<()>::new();
@@ -194,6 +217,21 @@ where
         self
     }
 
+    /// Assigns the loader to a resource class, at most `max` tasks of the
+    /// class run at the same time and the documents are processed by at most
+    /// `max` threads.
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the loader if it runs longer than `timeout`, the remaining files
+    /// are not loaded once it elapses.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     /// Registers the task with the Blueprint.
     pub fn register(self) -> Many<Document<R>> {
         let base = self.base.map(Arc::from);
@@ -201,12 +239,13 @@ where
         let task = GlobFiles::new(self.entry, self.watch, move |_, _, input: Input| {
             let bytes = input
                 .read()
-                .map_err(|e| FrontmatterError::Parse(e.into()))?;
+                .map_err(|e| FrontmatterError::Io(input.path.clone(), e))?;
 
-            let data = std::str::from_utf8(&bytes).map_err(FrontmatterError::Utf8)?;
+            let data = std::str::from_utf8(&bytes)
+                .map_err(|e| FrontmatterError::Utf8(input.path.clone(), e))?;
 
-            let (metadata, content) =
-                super::parse_yaml::<R>(data).map_err(FrontmatterError::Parse)?;
+            let (metadata, content) = super::parse_yaml::<R>(data)
+                .map_err(|e| FrontmatterError::Parse(input.path.clone(), e))?;
 
             let href = crate::output::source_to_href(&input.path, base.as_deref());
 
@@ -224,7 +263,9 @@ where
             ))
         });
 
-        self.blueprint.add_task_fine(task)
+        let handle = self.blueprint.add_task_fine(task);
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 }
 
@@ -239,7 +280,8 @@ where
     ///
     /// # Type Parameters
     ///
-    /// * `R`: The type to deserialize the frontmatter into. Must implement [`serde::Deserialize`].
+    /// * `R`: The type to deserialize the frontmatter into. Must implement [`serde::Deserialize`],
+    ///   and [`Clone`] so that documents whose content didn't change can be reused on a rebuild.
     ///
     /// # Example
     ///
@@ -262,7 +304,7 @@ where
     pub fn load_documents<R>(&mut self) -> DocumentLoader<'_, G, R>
     where
         G: Send + Sync + 'static,
-        R: DeserializeOwned + Send + Sync + 'static,
+        R: DeserializeOwned + Clone + Send + Sync + 'static,
     {
         DocumentLoader::new(self)
     }
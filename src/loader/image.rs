@@ -37,9 +37,11 @@
 //!     Ok(images)
 //! }
 //! ```
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter};
+use std::time::Duration;
 
 use camino::{Utf8Path, Utf8PathBuf};
 use glob::Pattern;
@@ -47,7 +49,8 @@ use image::{ExtendedColorType, ImageReader};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::engine::Many;
+use crate::blueprint::TaskOptions;
+use crate::engine::{Limit, Many};
 use crate::error::{BuildError, HauchiwaError};
 use crate::loader::{GlobFiles, Input, Store};
 use crate::{Blueprint, TaskContext};
@@ -76,7 +79,7 @@ pub enum ImageError {
 }
 
 /// Configuration for image compression.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Quality {
     /// Lossless compression.
     Lossless,
@@ -92,7 +95,7 @@ impl Default for Quality {
 }
 
 /// Supported output image formats with specific configuration.
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ImageFormat {
     #[default]
     WebP,
@@ -111,7 +114,7 @@ impl ImageFormat {
 }
 
 /// Represents a processed image asset with multiple formats.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Image {
     /// The default image path (usually the first configured format).
     pub default: Utf8PathBuf,
@@ -142,6 +145,7 @@ where
     G: Send + Sync,
 {
     blueprint: &'a mut Blueprint<G>,
+    options: TaskOptions,
     entry: Vec<String>,
     watch: Vec<Pattern>,
     formats: Vec<ImageFormat>,
@@ -154,6 +158,7 @@ where
     fn new(blueprint: &'a mut Blueprint<G>) -> Self {
         Self {
             blueprint,
+            options: TaskOptions::default(),
             entry: Vec::new(),
             watch: Vec::new(),
             formats: Vec::new(),
@@ -179,6 +184,21 @@ where
         self
     }
 
+    /// Assigns the loader to a resource class, at most `max` tasks of the
+    /// class run at the same time and the images are processed by at most
+    /// `max` threads.
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the loader if it runs longer than `timeout`, the remaining files
+    /// are not loaded once it elapses.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     /// Registers the task with the Blueprint.
     pub fn register(self) -> Many<Image> {
         let mut formats = self.formats;
@@ -198,7 +218,9 @@ where
             },
         );
 
-        self.blueprint.add_task_fine(task)
+        let handle = self.blueprint.add_task_fine(task);
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 }
 
@@ -230,13 +252,11 @@ fn process_image(
 ) -> Result<(Image, Vec<Utf8PathBuf>), ImageError> {
     let source_hash = file.hash.to_hex();
     let cache_dir = store.cache_dir.join(DIR_REL);
-    let dist_dir = store.out_dir.join(DIR_REL);
 
     let meta_file_name = format!("{}.meta.cbor", source_hash);
     let meta_file_path = cache_dir.join(&meta_file_name);
 
     fs::create_dir_all(&cache_dir)?;
-    fs::create_dir_all(&dist_dir)?;
 
     // Try to load serialized metadata
     let metadata = if meta_file_path.exists() {
@@ -266,14 +286,14 @@ fn process_image(
 
         let path_store = Utf8Path::new(DIR_STORE).join(&file_name);
         let path_cache = cache_dir.join(&file_name);
-        let path_dist = dist_dir.join(&file_name);
+        let path_rel = Utf8Path::new(DIR_REL).join(&file_name);
 
         if !path_cache.exists() {
             // cache miss
             cached = false;
         }
 
-        outputs.push((format, path_store, path_cache, path_dist));
+        outputs.push((format, path_store, path_cache, path_rel));
     }
 
     // FAST PATH: If metadata exists and all output formats are cached
@@ -282,22 +302,11 @@ fn process_image(
         let mut default_path = None;
         let mut dist_paths = Vec::new();
 
-        for (format, path_store, path_cache, path_dist) in outputs {
+        for (format, path_store, path_cache, path_rel) in outputs {
             // Ensure artifact is in dist
-            if !path_dist.exists() {
-                // hard link with fallback to copy
-                if std::fs::hard_link(&path_cache, &path_dist).is_err() {
-                    std::fs::copy(&path_cache, &path_dist)?;
-                }
-            }
+            store.publish(&path_cache, &path_rel)?;
 
-            dist_paths.push(
-                Utf8Path::new(DIR_REL).join(
-                    path_dist
-                        .file_name()
-                        .ok_or(ImageError::InvalidOutput("path_dist has no filename"))?,
-                ),
-            );
+            dist_paths.push(path_rel);
             sources.insert(format, path_store.clone());
 
             if default_path.is_none() {
@@ -334,7 +343,7 @@ fn process_image(
     let mut default_path = None;
     let mut dist_paths = Vec::new();
 
-    for (format, path_store, path_cache, path_dist) in outputs {
+    for (format, path_store, path_cache, path_rel) in outputs {
         if !path_cache.exists() {
             let cache_file = File::create(&path_cache)?;
             let mut writer = BufWriter::new(cache_file);
@@ -388,20 +397,9 @@ fn process_image(
             }
         }
 
-        if !path_dist.exists() {
-            // hard link with fallback to copy
-            if std::fs::hard_link(&path_cache, &path_dist).is_err() {
-                std::fs::copy(&path_cache, &path_dist)?;
-            }
-        }
+        store.publish(&path_cache, &path_rel)?;
 
-        dist_paths.push(
-            Utf8Path::new(DIR_REL).join(
-                path_dist
-                    .file_name()
-                    .ok_or(ImageError::InvalidOutput("path_dist has no filename"))?,
-            ),
-        );
+        dist_paths.push(path_rel);
         sources.insert(format, path_store.clone());
 
         if default_path.is_none() {
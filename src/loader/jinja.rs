@@ -1,13 +1,16 @@
 use std::collections::HashSet;
 
 use camino::{Utf8Path, Utf8PathBuf};
-use glob::{Pattern, glob};
+use glob::Pattern;
 use petgraph::graph::NodeIndex;
 
 use crate::Blueprint;
-use crate::core::{Dynamic, Store, TaskContext};
+use crate::core::{Dynamic, Hash32, Store, TaskContext};
 use crate::engine::{TrackerState, Tracking, TypedCoarse};
 use crate::error::HauchiwaError;
+use crate::loader::fingerprint_globs;
+use crate::stat::StatCache;
+use crate::vfs::Vfs;
 
 /// A loaded minijinja template environment.
 ///
@@ -83,15 +86,14 @@ where
     fn execute(
         &self,
         _: &TaskContext<G>,
-        _: &mut Store,
+        runtime: &mut Store,
         _: &[Dynamic],
     ) -> anyhow::Result<(Tracking, Self::Output)> {
         let mut env = minijinja::Environment::new();
 
         for glob_entry in &self.glob_entry {
-            for path in glob(glob_entry)? {
-                let path = Utf8PathBuf::try_from(path?)?;
-                let source = std::fs::read_to_string(&path)?;
+            for path in runtime.vfs.glob(glob_entry)? {
+                let source = String::from_utf8(runtime.vfs.read(&path)?.into())?;
 
                 let name = match &self.root {
                     Some(root) => path.strip_prefix(root).unwrap_or(&path).to_string(),
@@ -118,6 +120,10 @@ where
         // No upstream dependencies - always valid unless explicitly dirtied by a file change.
         true
     }
+
+    fn fingerprint(&self, vfs: &dyn Vfs, stats: &StatCache) -> anyhow::Result<Option<Hash32>> {
+        fingerprint_globs(vfs, stats, &self.glob_entry, &self.glob_watch).map(Some)
+    }
 }
 
 /// A builder for configuring the minijinja template loader.
@@ -35,13 +35,22 @@
 //! }
 //! ```
 
+use std::borrow::Cow;
+use std::time::Duration;
+
 use camino::Utf8Path;
 use glob::Pattern;
 use rolldown::{BundlerOptions, CodeSplittingMode, InputItem, RawMinifyOptions};
 use thiserror::Error;
 
+use crate::blueprint::TaskOptions;
 use crate::core::Hash32;
-use crate::{Blueprint, engine::Many, error::HauchiwaError, loader::GlobBundle};
+use crate::{
+    Blueprint,
+    engine::{Limit, Many},
+    error::HauchiwaError,
+    loader::GlobBundle,
+};
 
 /// Errors that can occur when compiling JavaScript files.
 #[derive(Debug, Error)]
@@ -69,6 +78,7 @@ where
     G: Send + Sync,
 {
     blueprint: &'a mut Blueprint<G>,
+    options: TaskOptions,
     entry_globs: Vec<String>,
     entry_patterns: Vec<Pattern>,
     watch_globs: Vec<Pattern>,
@@ -84,6 +94,7 @@ where
     pub(crate) fn new(blueprint: &'a mut Blueprint<G>) -> Self {
         Self {
             blueprint,
+            options: TaskOptions::default(),
             entry_globs: Vec::new(),
             entry_patterns: Vec::new(),
             watch_globs: Vec::new(),
@@ -135,6 +146,21 @@ where
         self
     }
 
+    /// Assigns the loader to a resource class, at most `max` tasks of the
+    /// class run at the same time and the scripts are processed by at most
+    /// `max` threads.
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the loader if it runs longer than `timeout`, the remaining files
+    /// are not loaded once it elapses.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     /// Registers the task with the Blueprint.
     pub fn register(self) -> Many<super::Script> {
         let watch_globs = if self.watch_globs.is_empty() {
@@ -164,7 +190,9 @@ where
             Ok(())
         });
 
-        self.blueprint.add_task_fine(task)
+        let handle = self.blueprint.add_task_fine(task);
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 }
 
@@ -191,7 +219,11 @@ where
     }
 }
 
-fn bundle_package_rolldown(cache_dir: &Utf8Path, package: &str, minify: bool) -> Result<Vec<u8>, ScriptError> {
+fn bundle_package_rolldown(
+    cache_dir: &Utf8Path,
+    package: &str,
+    minify: bool,
+) -> Result<Vec<u8>, ScriptError> {
     let temp_dir = cache_dir.join("tmp");
     std::fs::create_dir_all(&temp_dir)?;
 
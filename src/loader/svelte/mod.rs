@@ -58,9 +58,11 @@
 //! }
 //! ```
 use std::{
+    borrow::Cow,
     io::Write,
-    process::{Command, Stdio},
+    process::{Command, Output, Stdio},
     sync::{Arc, LazyLock},
+    time::Duration,
 };
 
 use camino::Utf8Path;
@@ -68,10 +70,11 @@ use glob::Pattern;
 use serde::{Serialize, de::DeserializeOwned};
 use thiserror::Error;
 
+use crate::blueprint::TaskOptions;
 use crate::core::Hash32;
 use crate::{
     Blueprint,
-    engine::Many,
+    engine::{CancelToken, Limit, Many},
     error::HauchiwaError,
     loader::{GlobBundle, Script},
 };
@@ -108,6 +111,8 @@ struct MappedJs {
 // Update the Prerender type alias to use the specific error
 type Prerender<P> = Arc<dyn Fn(&P) -> Result<String, SvelteError> + Send + Sync>;
 
+type Render<P> = Arc<dyn Fn(&P, &CancelToken) -> Result<String, SvelteError> + Send + Sync>;
+
 // The LazyLock now holds a specific Result type.
 static RUNTIME: LazyLock<Result<MappedJs, SvelteError>> = LazyLock::new(compile_svelte_runtime);
 
@@ -127,11 +132,30 @@ where
 {
     /// A closure that takes props `P` and returns the rendered HTML string.
     /// This is used for Server-Side Rendering (SSR).
+    ///
+    /// The `deno` process is killed when the calling task is cancelled, as
+    /// long as it's called on the thread running the task. Use
+    /// [`Svelte::render`] from parallel iterators.
     pub prerender: Prerender<P>,
     /// The initialization script for this specific component (client-side hydration).
     pub hydration: Script,
     /// The shared Svelte runtime library script.
     pub runtime: Script,
+    render: Render<P>,
+}
+
+impl<P> Svelte<P>
+where
+    P: serde::Serialize,
+{
+    /// Renders the component into an HTML string, like
+    /// [`prerender`](Self::prerender), killing the `deno` process once
+    /// `cancel` is cancelled. Pass the token from
+    /// [`TaskContext::cancel_token`](crate::TaskContext::cancel_token) when
+    /// rendering on threads other than the one running the task.
+    pub fn render(&self, props: &P, cancel: &CancelToken) -> Result<String, SvelteError> {
+        (self.render)(props, cancel)
+    }
 }
 
 /// A builder for configuring the Svelte loader task.
@@ -141,6 +165,7 @@ where
     P: Clone + DeserializeOwned + Serialize + 'static,
 {
     blueprint: &'a mut Blueprint<G>,
+    options: TaskOptions,
     entry_globs: Vec<String>,
     entry_patterns: Vec<Pattern>,
     watch_globs: Vec<Pattern>,
@@ -155,6 +180,7 @@ where
     fn new(blueprint: &'a mut Blueprint<G>) -> Self {
         Self {
             blueprint,
+            options: TaskOptions::default(),
             entry_globs: Vec::new(),
             entry_patterns: Vec::new(),
             watch_globs: Vec::new(),
@@ -181,6 +207,21 @@ where
         Ok(self)
     }
 
+    /// Assigns the loader to a resource class, at most `max` tasks of the
+    /// class run at the same time and the components are processed by at most
+    /// `max` threads.
+    pub fn limit(mut self, class: impl Into<Cow<'static, str>>, max: usize) -> Self {
+        self.options.limit = Some(Limit::new(class, max));
+        self
+    }
+
+    /// Fails the loader if it runs longer than `timeout`, any child process
+    /// it is waiting for is killed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
     /// Registers the task with the Blueprint.
     pub fn register(self) -> Many<Svelte<P>> {
         let watch_globs = if self.watch_globs.is_empty() {
@@ -189,7 +230,7 @@ where
             self.watch_globs
         };
 
-        let task = GlobBundle::new(self.entry_globs, watch_globs, move |_, store, input| {
+        let task = GlobBundle::new(self.entry_globs, watch_globs, move |ctx, store, input| {
             let runtime = match RUNTIME.as_ref() {
                 Ok(runtime) => {
                     let srcmap = store.save(&runtime.map, "js.map")?;
@@ -206,12 +247,12 @@ where
             store.register("svelte/internal/disclose-version", runtime.as_str());
 
             // Compile the SSR script
-            let server = compile_svelte_server(&input.path)?;
+            let server = compile_svelte_server(&input.path, ctx.cancel_token())?;
             let anchor = Hash32::hash(&server);
 
             // Compile lean browser glue
             let client = {
-                let client = compile_svelte_init(&input.path, anchor)?;
+                let client = compile_svelte_init(&input.path, anchor, ctx.cancel_token())?;
                 let srcmap = store.save(&client.map, "js.map")?;
                 let script = format!("{}\n//# sourceMappingURL={}", client.code, srcmap);
                 store.save(script.as_bytes(), "js")?
@@ -219,18 +260,22 @@ where
 
             // With the compiled SSR script we can now pre-render the
             // component on demand.
-            let prerender = Arc::new({
+            let render: Render<P> = Arc::new({
                 let anchor = anchor.to_hex();
 
-                move |props: &P| {
+                move |props: &P, cancel: &CancelToken| {
                     let json = serde_json::to_string(props)?;
-                    let html = run_ssr(&server, &json)?;
+                    let html = run_ssr(&server, &json, cancel)?;
 
                     Ok(format!(
                         "<div class='_{anchor}' data-props='{json}'>{html}</div>"
                     ))
                 }
             });
+            let prerender = Arc::new({
+                let render = render.clone();
+                move |props: &P| render(props, &CancelToken::current())
+            });
 
             Ok((
                 anchor,
@@ -239,13 +284,16 @@ where
                     prerender,
                     hydration: Script { path: client },
                     runtime: Script { path: runtime },
+                    render,
                 },
             ))
         });
 
         let task = task.require(crate::preflight::Requirement::Binary("deno"));
 
-        self.blueprint.add_task_fine(task)
+        let handle = self.blueprint.add_task_fine(task);
+        self.blueprint.set_options(handle.index(), self.options);
+        handle
     }
 }
 
@@ -286,29 +334,43 @@ where
     }
 }
 
-fn compile_svelte_server(file: &Utf8Path) -> Result<String, SvelteError> {
-    const SERVER: &[u8] = include_bytes!("./server.ts");
-
-    let mut child = Command::new("deno")
-        .arg("run")
-        .arg("--quiet")
-        .arg("--allow-env")
-        .arg("--allow-read")
-        .arg("--allow-run")
-        .arg("-")
-        .arg(file.as_str())
+/// Runs `command` with `script` piped to its standard input, killing it once
+/// `cancel` is cancelled.
+fn run_script(
+    command: &mut Command,
+    script: &[u8],
+    cancel: &CancelToken,
+) -> Result<Output, SvelteError> {
+    let mut child = command
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()?;
 
     {
-        let stdin = child.stdin.as_mut().ok_or(SvelteError::StdinCapture)?;
-        stdin.write_all(SERVER)?;
+        // dropped at the end of the block, so deno sees the end of the script
+        let mut stdin = child.stdin.take().ok_or(SvelteError::StdinCapture)?;
+        stdin.write_all(script)?;
         stdin.flush()?;
     }
 
-    let output = child.wait_with_output()?;
+    Ok(cancel.wait(child)?)
+}
+
+fn compile_svelte_server(file: &Utf8Path, cancel: &CancelToken) -> Result<String, SvelteError> {
+    const SERVER: &[u8] = include_bytes!("./server.ts");
+
+    let mut command = Command::new("deno");
+    command
+        .arg("run")
+        .arg("--quiet")
+        .arg("--allow-env")
+        .arg("--allow-read")
+        .arg("--allow-run")
+        .arg("-")
+        .arg(file.as_str());
+
+    let output = run_script(&mut command, SERVER, cancel)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -318,27 +380,21 @@ fn compile_svelte_server(file: &Utf8Path) -> Result<String, SvelteError> {
     Ok(String::from_utf8(output.stdout)?)
 }
 
-fn run_ssr(server: &str, props: &str) -> Result<String, SvelteError> {
+/// Renders a component, `cancel` belongs to the task rendering the page, not
+/// to this loader.
+fn run_ssr(server: &str, props: &str, cancel: &CancelToken) -> Result<String, SvelteError> {
     const SSR: &str = include_str!("./ssr.ts");
 
-    let mut child = Command::new("deno")
+    let mut command = Command::new("deno");
+    command
         .arg("run")
         .arg("--allow-env")
         .arg("--quiet")
         .arg("-")
-        .arg(props)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
-
-    {
-        let stdin = child.stdin.as_mut().ok_or(SvelteError::StdinCapture)?;
-        stdin.write_all(SSR.replace("__PLACEHOLDER__", server).as_bytes())?;
-        stdin.flush()?;
-    }
+        .arg(props);
 
-    let output = child.wait_with_output()?;
+    let script = SSR.replace("__PLACEHOLDER__", server);
+    let output = run_script(&mut command, script.as_bytes(), cancel)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -348,10 +404,15 @@ fn run_ssr(server: &str, props: &str) -> Result<String, SvelteError> {
     Ok(String::from_utf8(output.stdout)?)
 }
 
-fn compile_svelte_init(file: &Utf8Path, hash_class: Hash32) -> Result<MappedJs, SvelteError> {
+fn compile_svelte_init(
+    file: &Utf8Path,
+    hash_class: Hash32,
+    cancel: &CancelToken,
+) -> Result<MappedJs, SvelteError> {
     const INIT: &[u8] = include_bytes!("./init.ts");
 
-    let mut child = Command::new("deno")
+    let mut command = Command::new("deno");
+    command
         .arg("run")
         .arg("--quiet")
         .arg("--allow-env")
@@ -359,19 +420,9 @@ fn compile_svelte_init(file: &Utf8Path, hash_class: Hash32) -> Result<MappedJs,
         .arg("--allow-run")
         .arg("-")
         .arg(file.canonicalize()?)
-        .arg(hash_class.to_hex())
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
-
-    {
-        let stdin = child.stdin.as_mut().ok_or(SvelteError::StdinCapture)?;
-        stdin.write_all(INIT)?;
-        stdin.flush()?;
-    }
+        .arg(hash_class.to_hex());
 
-    let output = child.wait_with_output()?;
+    let output = run_script(&mut command, INIT, cancel)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -384,25 +435,18 @@ fn compile_svelte_init(file: &Utf8Path, hash_class: Hash32) -> Result<MappedJs,
 fn compile_svelte_runtime() -> Result<MappedJs, SvelteError> {
     const RT: &[u8] = include_bytes!("./rt.ts");
 
-    let mut child = Command::new("deno")
+    let mut command = Command::new("deno");
+    command
         .arg("run")
         .arg("--quiet")
         .arg("--allow-env")
         .arg("--allow-read")
         .arg("--allow-run")
-        .arg("-")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
-
-    {
-        let stdin = child.stdin.as_mut().ok_or(SvelteError::StdinCapture)?;
-        stdin.write_all(RT)?;
-        stdin.flush()?;
-    }
+        .arg("-");
 
-    let output = child.wait_with_output()?;
+    // The result is kept for the lifetime of the process, so the compilation
+    // is never cancelled together with the build that happened to start it.
+    let output = run_script(&mut command, RT, &CancelToken::default())?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -448,3 +492,38 @@ fn parse_deno_output(output: &[u8]) -> Result<MappedJs, SvelteError> {
         map: map_bytes.to_vec(),
     })
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancel_script_on_worker() {
+        use std::time::Instant;
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let cancel = CancelToken::new();
+        let start = Instant::now();
+
+        // The token is passed explicitly, the worker doesn't know the task.
+        let (sender, receiver) = std::sync::mpsc::channel();
+        pool.spawn({
+            let cancel = cancel.clone();
+            move || {
+                let result = run_script(Command::new("sleep").arg("10"), b"", &cancel);
+                sender.send(result.map(|_| ())).unwrap();
+            }
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        cancel.cancel();
+        let result = receiver.recv().unwrap();
+
+        assert!(matches!(result, Err(SvelteError::Io(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -5,6 +5,8 @@
 
 use camino::Utf8Component;
 use camino::{Utf8Path, Utf8PathBuf};
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::Many;
@@ -405,7 +407,7 @@ impl OutputTargetBuilder {
 }
 
 /// The content of an [`Output`] file.
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub enum OutputData {
     /// Text content (UTF-8).
     Utf8(String),
@@ -427,7 +429,7 @@ impl AsRef<[u8]> for OutputData {
 /// A `Output` is a common output type for tasks that generate HTML, TXT, or
 /// other static assets. The build system collects all `Output` instances and
 /// writes them to the filesystem.
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct Output {
     /// The destination path of the file, relative to the `dist` directory.
     pub path: Utf8PathBuf,
@@ -608,6 +610,63 @@ impl OutputBuilder {
     }
 }
 
+/// Selects the outputs built by [`Website::build_filtered`](crate::Website::build_filtered).
+///
+/// Outputs are matched by their path in `dist`, e.g. `posts/hello/index.html`,
+/// using globs where `*` doesn't cross directories and `**` does.
+///
+/// # Example
+///
+/// ```rust
+/// use hauchiwa::output::OutputFilter;
+///
+/// let filter = OutputFilter::href("/posts/**/")?.or(OutputFilter::glob("feed.xml")?);
+/// assert!(filter.matches("posts/2024/hello/index.html".into()));
+/// assert!(filter.matches("feed.xml".into()));
+/// assert!(!filter.matches("about/index.html".into()));
+/// # Ok::<(), hauchiwa::error::HauchiwaError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutputFilter {
+    patterns: Vec<Pattern>,
+}
+
+impl OutputFilter {
+    /// Matches outputs whose path in `dist` matches `pattern`.
+    pub fn glob(pattern: &str) -> Result<Self, crate::error::HauchiwaError> {
+        Ok(Self {
+            patterns: vec![Pattern::new(pattern.trim_start_matches('/'))?],
+        })
+    }
+
+    /// Matches outputs served at an href matching `pattern`. Hrefs ending
+    /// with `/` are served from the `index.html` in that directory.
+    pub fn href(pattern: &str) -> Result<Self, crate::error::HauchiwaError> {
+        match pattern.ends_with('/') || pattern.is_empty() {
+            true => Self::glob(&format!("{pattern}index.html")),
+            false => Self::glob(pattern),
+        }
+    }
+
+    /// Matches the outputs matched by either filter.
+    #[must_use]
+    pub fn or(mut self, other: OutputFilter) -> Self {
+        self.patterns.extend(other.patterns);
+        self
+    }
+
+    /// Returns `true` if the output at `path` in `dist` is selected.
+    pub fn matches(&self, path: &Utf8Path) -> bool {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches_with(path.as_str(), options))
+    }
+}
+
 /// A trait for handles that can be flattened into a list of Output references.
 pub trait OutputHandle: Handle {
     /// Extracts a list of `Output` references from the handle's resolved value.
@@ -658,6 +717,7 @@ impl OutputHandle for Many<Output> {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
 
@@ -799,4 +859,16 @@ mod tests {
         assert_eq!(page.path, Utf8Path::new("posts/hello/index.html"));
         Ok(())
     }
+
+    #[test]
+    fn test_output_filter() {
+        let filter = OutputFilter::href("/posts/*/").unwrap();
+        assert!(filter.matches(Utf8Path::new("posts/hello/index.html")));
+        assert!(!filter.matches(Utf8Path::new("posts/2024/hello/index.html")));
+        assert!(!filter.matches(Utf8Path::new("posts/index.html")));
+
+        let filter = OutputFilter::href("/").unwrap();
+        assert!(filter.matches(Utf8Path::new("index.html")));
+        assert!(!filter.matches(Utf8Path::new("posts/index.html")));
+    }
 }
@@ -0,0 +1,239 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use camino::Utf8PathBuf;
+#[cfg(any(test, feature = "live"))]
+use serde::Serialize;
+
+#[cfg(any(test, feature = "live"))]
+use crate::error::{BuildError, LoaderError};
+#[cfg(any(test, feature = "live"))]
+use crate::snapshot::CommitReport;
+
+/// Path of the WebSocket endpoint the live-reload script connects to, when
+/// it's served by the HTTP server.
+pub(crate) const RELOAD_PATH: &str = "/_hauchiwa/ws";
+
+/// Returns the script connecting a page to the live-reload server, listening
+/// on `port` or, without one, on the HTTP server the page was loaded from.
+pub(crate) fn refresh_script(port: Option<u16>) -> String {
+    let url = match port {
+        Some(port) => {
+            format!(r#"const url = `ws://${{location.hostname || "localhost"}}:{port}`;"#)
+        }
+        None => format!(
+            r#"const url = new URL("{RELOAD_PATH}", location.href);
+url.protocol = url.protocol === "https:" ? "wss:" : "ws:";"#
+        ),
+    };
+    // Scoped to a function, so that nothing clashes with scripts of the page.
+    format!(
+        "\n(() => {{\n{url}\n{}}})();\n",
+        include_str!("server/reload.js")
+    )
+}
+
+/// A message sent to the pages connected to the live-reload server, handled
+/// by the script returned from [`refresh_script`].
+#[cfg(any(test, feature = "live"))]
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum ReloadMessage {
+    /// Reloads the pages showing one of the changed `paths` in `dist`, or
+    /// every page if they aren't known. With `css` only the stylesheets of
+    /// these pages changed, which are swapped without reloading the page.
+    Reload {
+        paths: Option<Vec<Utf8PathBuf>>,
+        css: bool,
+    },
+    /// Shows the errors of a failed rebuild in an overlay, until the next
+    /// successful rebuild.
+    Error { errors: Vec<Failure> },
+}
+
+/// A single error shown in the overlay.
+#[cfg(any(test, feature = "live"))]
+#[derive(Debug, Serialize)]
+pub(crate) struct Failure {
+    /// Name of the task that failed, if the error came from a task.
+    task: Option<String>,
+    message: String,
+    /// Source file that failed to load, if known.
+    path: Option<Utf8PathBuf>,
+}
+
+#[cfg(any(test, feature = "live"))]
+impl ReloadMessage {
+    /// Reloads every page.
+    pub(crate) fn all() -> Self {
+        Self::Reload {
+            paths: None,
+            css: false,
+        }
+    }
+
+    /// Reloads the pages written or removed by a commit.
+    pub(crate) fn changed(report: &CommitReport, css: bool) -> Self {
+        let paths = report
+            .written()
+            .chain(&report.removed)
+            .map(|file| file.path.clone())
+            // Content-addressed assets are new files, the pages using them
+            // changed as well.
+            .filter(|path| !path.starts_with("hash"))
+            .collect();
+
+        Self::Reload {
+            paths: Some(paths),
+            css,
+        }
+    }
+
+    /// Shows the errors of a failed rebuild.
+    pub(crate) fn failed<'a>(errors: impl IntoIterator<Item = &'a BuildError>) -> Self {
+        fn collect(error: &BuildError, out: &mut Vec<Failure>) {
+            match error {
+                BuildError::Tasks(errors) => {
+                    for error in errors {
+                        collect(error, out);
+                    }
+                }
+                BuildError::Task(task, error) => out.push(Failure {
+                    task: Some(task.clone()),
+                    message: format!("{error:#}"),
+                    path: error
+                        .chain()
+                        .filter_map(|e| e.downcast_ref::<LoaderError>())
+                        .find_map(LoaderError::path)
+                        .map(ToOwned::to_owned),
+                }),
+                BuildError::Timeout(task, _) => out.push(Failure {
+                    task: Some(task.clone()),
+                    message: error.to_string(),
+                    path: None,
+                }),
+                error => out.push(Failure {
+                    task: None,
+                    message: error.to_string(),
+                    path: None,
+                }),
+            }
+        }
+
+        let mut out = Vec::new();
+        for error in errors {
+            collect(error, &mut out);
+        }
+        Self::Error { errors: out }
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Configuration of the development server started by
+/// [`Website::watch`](crate::Website::watch).
+///
+/// Watch mode runs an HTTP server serving the contents of `dist`, available
+/// with the `server` feature, which also tells open pages to reload after a
+/// rebuild over a WebSocket. Without the feature only a WebSocket server is
+/// started, on a port of its own.
+///
+/// Each field has a sensible default, override individual fields and set the
+/// configuration with [`Blueprint::set_server`](crate::Blueprint::set_server).
+///
+/// # Example
+///
+/// ```rust
+/// use std::net::Ipv4Addr;
+/// use hauchiwa::{Blueprint, ServerConfig};
+///
+/// let config = Blueprint::<()>::new().set_server(ServerConfig {
+///     host: Ipv4Addr::UNSPECIFIED.into(),
+///     port: 3000,
+///     headers: vec![("Cache-Control".into(), "no-store".into())],
+///     ..ServerConfig::default()
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Address the servers listen on (default: `127.0.0.1`). Use `0.0.0.0`
+    /// to reach the site from other devices on the network.
+    pub host: IpAddr,
+    /// Port of the HTTP server (default: `8080`).
+    pub port: u16,
+    /// Port of the live-reload WebSocket server. By default `1337` is used,
+    /// or any free port if it's already taken. Unused with the `server`
+    /// feature, pages then connect to the HTTP server at `/_hauchiwa/ws`.
+    pub live_reload_port: Option<u16>,
+    /// Headers added to every HTTP response, as name and value pairs.
+    pub headers: Vec<(String, String)>,
+    /// Page in `dist` served with status 404 for paths with no matching file
+    /// (default: `404.html`).
+    pub not_found: Option<Utf8PathBuf>,
+    /// Page in `dist` served with status 200 for paths with no matching file,
+    /// e.g. `index.html` for a single-page application. Takes precedence over
+    /// [`not_found`](Self::not_found).
+    pub fallback: Option<Utf8PathBuf>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 8080,
+            live_reload_port: None,
+            headers: Vec::new(),
+            not_found: Some(Utf8PathBuf::from("404.html")),
+            fallback: None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::DistFile;
+
+    #[test]
+    fn test_reload_message() {
+        let file = |path: &str| DistFile {
+            path: path.into(),
+            task: None,
+        };
+        let report = CommitReport {
+            new: vec![file("hash/abc.css")],
+            changed: vec![file("index.html")],
+            unchanged: vec![file("about/index.html")],
+            removed: vec![file("hash/def.css"), file("old/index.html")],
+        };
+
+        assert_eq!(
+            ReloadMessage::changed(&report, true).to_json(),
+            r#"{"type":"reload","paths":["index.html","old/index.html"],"css":true}"#
+        );
+        assert_eq!(
+            ReloadMessage::all().to_json(),
+            r#"{"type":"reload","paths":null,"css":false}"#
+        );
+    }
+
+    #[test]
+    fn test_error_message() {
+        let file = LoaderError::File("content/a.md".into(), anyhow::anyhow!("bad front matter"));
+        let errors = [BuildError::Tasks(vec![
+            BuildError::Task("posts".into(), file.into()),
+            BuildError::Task("feed".into(), anyhow::anyhow!("inner").context("outer")),
+        ])];
+
+        assert_eq!(
+            ReloadMessage::failed(&errors).to_json(),
+            concat!(
+                r#"{"type":"error","errors":["#,
+                r#"{"task":"posts","message":"Couldn't load file 'content/a.md'.\nbad front matter","path":"content/a.md"},"#,
+                r#"{"task":"feed","message":"outer: inner","path":null}]}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,110 @@
+const socket = new WebSocket(url);
+
+socket.addEventListener("message", event => {
+    let message;
+    try {
+        message = JSON.parse(event.data);
+    } catch {
+        message = { type: "reload" };
+    }
+    if (message.type === "error") return showErrors(message.errors);
+    if (message.type !== "reload") return;
+    hideErrors();
+
+    // Without the changed paths it's not known which pages are affected.
+    if (!message.paths) return location.reload();
+
+    // Files other than pages could be used by any page.
+    const pages = message.paths.filter(path => path.endsWith(".html"));
+    if (pages.length < message.paths.length) return location.reload();
+
+    let page = decodeURIComponent(location.pathname).replace(/^\/+/, "");
+    if (page === "" || page.endsWith("/")) page += "index.html";
+    if (!pages.includes(page)) return;
+
+    if (message.css) swapStylesheets().catch(() => location.reload());
+    else location.reload();
+});
+
+// Only the stylesheets of the page changed, their new paths are taken from
+// the rebuilt page and swapped in place, keeping the scroll position.
+async function swapStylesheets() {
+    const response = await fetch(location.href, { cache: "no-store" });
+    const next = new DOMParser().parseFromString(await response.text(), "text/html");
+
+    const selector = 'link[rel="stylesheet"]';
+    const links = document.querySelectorAll(selector);
+    const hrefs = [...next.querySelectorAll(selector)].map(link => link.getAttribute("href"));
+    if (links.length !== hrefs.length) return location.reload();
+
+    links.forEach((link, i) => {
+        if (link.getAttribute("href") !== hrefs[i]) link.setAttribute("href", hrefs[i]);
+    });
+}
+
+const overlayId = "hauchiwa-error-overlay";
+const overlayStyle = `
+.backdrop { position: fixed; inset: 0; z-index: 2147483647; overflow: auto;
+    background: rgba(0, 0, 0, 0.66); font: 14px/1.5 ui-monospace, monospace; }
+.panel { max-width: 960px; margin: 48px auto; padding: 24px 32px; color: #d8d8d8;
+    background: #181818; border-top: 6px solid #ff5555; border-radius: 6px; }
+h2 { margin: 0 0 16px; color: #ff5555; font-size: 18px; }
+button { float: right; color: inherit; background: none; border: 0; font-size: 18px; cursor: pointer; }
+.task { color: #ffd866; }
+.path { color: #78dce8; }
+pre { margin: 8px 0 24px; white-space: pre-wrap; }
+`;
+
+// Shows the errors of a failed rebuild over the page, until it's dismissed
+// or the next rebuild succeeds.
+function showErrors(errors) {
+    hideErrors();
+    const host = document.createElement("div");
+    host.id = overlayId;
+    const root = host.attachShadow({ mode: "open" });
+
+    const backdrop = document.createElement("div");
+    backdrop.className = "backdrop";
+    const panel = document.createElement("div");
+    panel.className = "panel";
+
+    const close = document.createElement("button");
+    close.textContent = "\u00d7";
+    close.title = "Dismiss";
+    close.addEventListener("click", hideErrors);
+    const title = document.createElement("h2");
+    title.textContent = "Build failed";
+    panel.append(close, title);
+
+    for (const error of errors) {
+        const header = document.createElement("div");
+        if (error.task) {
+            const task = document.createElement("span");
+            task.className = "task";
+            task.textContent = `Task '${error.task}'`;
+            header.append(task);
+        }
+        if (error.path) {
+            const path = document.createElement("span");
+            path.className = "path";
+            path.textContent = ` ${error.path}`;
+            header.append(path);
+        }
+        const text = document.createElement("pre");
+        text.textContent = error.message;
+        panel.append(header, text);
+    }
+
+    const style = document.createElement("style");
+    style.textContent = overlayStyle;
+    backdrop.addEventListener("click", event => event.target === backdrop && hideErrors());
+    backdrop.append(panel);
+    root.append(style, backdrop);
+    (document.body ?? document.documentElement).append(host);
+}
+
+function hideErrors() {
+    document.getElementById(overlayId)?.remove();
+}
+
+document.addEventListener("keydown", event => event.key === "Escape" && hideErrors());
@@ -1,4 +1,5 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::io;
 use std::path::Path;
@@ -9,7 +10,8 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::core::Hash32;
-use crate::output::Output;
+use crate::output::{Output, OutputData, OutputFilter};
+use crate::vfs::Vfs;
 
 /// What a snapshot entry represents in `dist`.
 ///
@@ -19,6 +21,8 @@ pub(crate) enum SnapshotEntry {
     /// An [`Output`] file whose content is held in memory and written by `commit()`.
     Page {
         task: String,
+        /// The task in the build graph which produced the page.
+        node: NodeIndex,
         output: Output,
         /// Blake3 hash of `output.data`, computed once at insert time.
         /// Used by `commit_diff` to skip unchanged pages without touching the disk.
@@ -86,7 +90,7 @@ impl Snapshot {
     /// Inserts an [`Output`] page. Fails if another producer already claimed the same dist path.
     pub(crate) fn insert_page(
         &mut self,
-        _node: NodeIndex,
+        node: NodeIndex,
         task_name: &str,
         output: Output,
     ) -> Result<(), crate::error::BuildError> {
@@ -105,6 +109,7 @@ impl Snapshot {
             path,
             SnapshotEntry::Page {
                 task: task_name.to_string(),
+                node,
                 content_hash,
                 output,
             },
@@ -179,11 +184,9 @@ impl Snapshot {
     ///    what is already on disk (blake3 comparison).
     ///
     /// [`SnapshotEntry::HashAsset`] and [`SnapshotEntry::StaticFile`] entries
-    /// are already on disk before `commit()` is called.
-    pub(crate) fn commit(&self, dist: &camino::Utf8Path) -> io::Result<()> {
-        let dist = dist.as_std_path();
-        fs::create_dir_all(dist)?;
-
+    /// are already on disk before `commit()` is called. Without a previous
+    /// snapshot hash assets are reported as new unless they exist in `dist`.
+    pub(crate) fn commit(&self, dist: &camino::Utf8Path) -> io::Result<CommitReport> {
         tracing::debug!(
             "commit: {} total entries ({} pages, {} hash assets, {} static files)",
             self.entries.len(),
@@ -201,23 +204,9 @@ impl Snapshot {
                 .count(),
         );
 
-        let desired: HashSet<Utf8PathBuf> = self.entries.keys().cloned().collect();
-        let removed = remove_stale(dist, Utf8Path::new(""), &desired)?;
-        if removed > 0 {
-            tracing::info!("removed {} stale file(s) from dist", removed);
-        }
-
-        write_pages(
-            dist,
-            self.entries.iter().filter_map(|(path, entry)| match entry {
-                SnapshotEntry::Page {
-                    output,
-                    content_hash,
-                    ..
-                } => Some((path, output, content_hash)),
-                _ => None,
-            }),
-        )
+        let report = self.diff(Baseline::Disk, dist)?;
+        self.apply(&report, dist)?;
+        Ok(report)
     }
 
     /// Incremental diff against a previous snapshot - intended for watch rebuilds.
@@ -228,82 +217,26 @@ impl Snapshot {
     /// 1. Deletes files present in `prev` but absent from `self`.
     /// 2. Writes pages that are new or whose content hash changed.
     ///    Pages with identical hashes are skipped entirely - no disk read needed.
-    pub(crate) fn commit_diff(&self, prev: &Snapshot, dist: &camino::Utf8Path) -> io::Result<()> {
-        let dist = dist.as_std_path();
-        fs::create_dir_all(dist)?;
-
+    #[cfg(any(test, feature = "live"))]
+    pub(crate) fn commit_diff(
+        &self,
+        prev: &Snapshot,
+        dist: &camino::Utf8Path,
+    ) -> io::Result<CommitReport> {
         tracing::debug!(
             "commit_diff: {} prev entries -> {} new entries",
             prev.entries.len(),
             self.entries.len(),
         );
 
-        // Delete files that disappeared from the snapshot.
-        let mut removed = 0;
-        let mut dirs_to_prune: HashSet<std::path::PathBuf> = HashSet::new();
-        for path in prev.entries.keys() {
-            if !self.entries.contains_key(path) {
-                let abs = dist.join(path.as_std_path());
-                tracing::debug!("removing stale dist file: {}", path);
-                match fs::remove_file(&abs) {
-                    Ok(()) => removed += 1,
-                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                        tracing::debug!("stale file already gone: {}", path);
-                    }
-                    Err(e) => return Err(e),
-                }
-                if let Some(parent) = abs.parent() {
-                    dirs_to_prune.insert(parent.to_path_buf());
-                }
-            }
-        }
-        if removed > 0 {
-            tracing::info!("removed {} stale file(s) from dist", removed);
-            prune_empty_dirs(dist, dirs_to_prune)?;
-        }
-
-        // Write pages that are new or whose content changed.
-        write_pages(
-            dist,
-            self.entries.iter().filter_map(|(path, entry)| {
-                let SnapshotEntry::Page {
-                    output,
-                    content_hash,
-                    ..
-                } = entry
-                else {
-                    return None;
-                };
-                match prev.entries.get(path) {
-                    None => {
-                        tracing::debug!("new page: {}", path);
-                        Some((path, output, content_hash))
-                    }
-                    Some(SnapshotEntry::Page {
-                        content_hash: prev_hash,
-                        ..
-                    }) => {
-                        let abs_path = dist.join(path.as_std_path());
-                        if prev_hash != content_hash || !abs_path.exists() {
-                            tracing::debug!("changed or missing page: {}", path);
-                            Some((path, output, content_hash))
-                        } else {
-                            tracing::debug!("unchanged page, skipping: {}", path);
-                            None
-                        }
-                    }
-                    _ => {
-                        tracing::debug!("new page (replaced non-page entry): {}", path);
-                        Some((path, output, content_hash))
-                    }
-                }
-            }),
-        )
+        let report = self.diff(Baseline::Snapshot(prev), dist)?;
+        self.apply(&report, dist)?;
+        Ok(report)
     }
 
     /// Converts this snapshot into a slim, serializable form suitable for
     /// persisting to disk. Page output data is not included - only the
-    /// content hash is retained for future diffing.
+    /// content hash and the producing task are retained for future diffing.
     pub(crate) fn to_meta(&self) -> SnapshotMeta {
         let entries = self
             .entries
@@ -319,7 +252,24 @@ impl Snapshot {
                 (path.to_string(), meta_entry)
             })
             .collect();
-        SnapshotMeta { entries }
+        let producers = self
+            .entries
+            .iter()
+            .filter_map(|(path, entry)| Some((path.to_string(), entry.task()?.to_string())))
+            .collect();
+        let nodes = self
+            .entries
+            .iter()
+            .filter_map(|(path, entry)| match entry {
+                SnapshotEntry::Page { node, .. } => Some((path.to_string(), node.index())),
+                _ => None,
+            })
+            .collect();
+        SnapshotMeta {
+            entries,
+            producers,
+            nodes,
+        }
     }
 
     /// Incremental diff against a persisted snapshot - intended for cold-start
@@ -330,33 +280,202 @@ impl Snapshot {
         &self,
         prev: &SnapshotMeta,
         dist: &camino::Utf8Path,
-    ) -> io::Result<()> {
-        let dist = dist.as_std_path();
-        fs::create_dir_all(dist)?;
-
+    ) -> io::Result<CommitReport> {
         tracing::debug!(
             "commit_diff_meta: {} prev entries -> {} new entries",
             prev.entries.len(),
             self.entries.len(),
         );
 
-        // Delete files that disappeared from the snapshot.
-        let mut removed = 0;
-        let mut dirs_to_prune: HashSet<std::path::PathBuf> = HashSet::new();
-        for path in prev.entries.keys() {
-            if !self.entries.contains_key(Utf8Path::new(path)) {
-                let abs = dist.join(path.as_str());
-                tracing::debug!("removing stale dist file: {}", path);
-                match fs::remove_file(&abs) {
-                    Ok(()) => removed += 1,
-                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                        tracing::debug!("stale file already gone: {}", path);
+        let report = self.diff(Baseline::Meta(prev), dist)?;
+        self.apply(&report, dist)?;
+        Ok(report)
+    }
+
+    /// Drops the pages whose path doesn't satisfy `keep`, other entries are
+    /// left untouched.
+    pub(crate) fn retain_pages(&mut self, mut keep: impl FnMut(&Utf8Path) -> bool) {
+        self.entries.retain(|path, entry| match entry {
+            SnapshotEntry::Page { .. } => keep(path),
+            _ => true,
+        });
+    }
+
+    /// Commit of a snapshot that only holds the outputs selected by `filter`,
+    /// intended for filtered builds.
+    ///
+    /// Semantics are identical to [`commit_diff_meta`](Self::commit_diff_meta),
+    /// except that files outside of `filter` are never deleted, and neither
+    /// are hash assets, which may still be referenced by unselected pages.
+    /// Without a previous snapshot nothing is deleted at all.
+    pub(crate) fn commit_filtered(
+        &self,
+        prev: Option<&SnapshotMeta>,
+        dist: &camino::Utf8Path,
+        filter: &OutputFilter,
+    ) -> io::Result<CommitReport> {
+        let baseline = match prev {
+            Some(prev) => Baseline::Meta(prev),
+            None => Baseline::Disk,
+        };
+
+        let mut report = self.diff(baseline, dist)?;
+        report.removed.retain(|file| {
+            prev.and_then(|prev| prev.entries.get(file.path.as_str()))
+                .is_some_and(|entry| !matches!(entry, MetaEntry::HashAsset))
+                && filter.matches(&file.path)
+        });
+
+        self.apply(&report, dist)?;
+        Ok(report)
+    }
+
+    /// Computes what committing this snapshot on top of `baseline` would
+    /// change in `dist`, without modifying anything on disk.
+    pub(crate) fn diff(
+        &self,
+        baseline: Baseline<'_>,
+        dist: &camino::Utf8Path,
+    ) -> io::Result<CommitReport> {
+        let mut report = CommitReport::default();
+
+        // Files that disappeared from the snapshot.
+        match baseline {
+            Baseline::Disk => {
+                let desired: HashSet<Utf8PathBuf> = self.entries.keys().cloned().collect();
+                let mut stale = Vec::new();
+                find_stale(dist.as_std_path(), Utf8Path::new(""), &desired, &mut stale)?;
+                report.removed = stale
+                    .into_iter()
+                    .map(|path| DistFile { path, task: None })
+                    .collect();
+            }
+            #[cfg(any(test, feature = "live"))]
+            Baseline::Snapshot(prev) => {
+                for (path, entry) in &prev.entries {
+                    if !self.entries.contains_key(path) {
+                        report.removed.push(DistFile {
+                            path: path.clone(),
+                            task: entry.task().map(String::from),
+                        });
                     }
-                    Err(e) => return Err(e),
                 }
-                if let Some(parent) = abs.parent() {
-                    dirs_to_prune.insert(parent.to_path_buf());
+            }
+            Baseline::Meta(prev) => {
+                for path in prev.entries.keys() {
+                    if !self.entries.contains_key(Utf8Path::new(path)) {
+                        report.removed.push(DistFile {
+                            path: Utf8PathBuf::from(path),
+                            task: prev.producers.get(path).cloned(),
+                        });
+                    }
+                }
+            }
+        }
+
+        // Pages on disk are hashed to compare them, which is done in parallel.
+        let changes = self
+            .entries
+            .par_iter()
+            .filter_map(|(path, entry)| self.classify(path, entry, &baseline, dist).transpose())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        for (change, file) in changes {
+            match change {
+                Change::New => report.new.push(file),
+                Change::Changed => report.changed.push(file),
+                Change::Unchanged => report.unchanged.push(file),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Compares a single entry with `baseline`, static files are skipped.
+    fn classify(
+        &self,
+        path: &Utf8Path,
+        entry: &SnapshotEntry,
+        baseline: &Baseline<'_>,
+        dist: &camino::Utf8Path,
+    ) -> io::Result<Option<(Change, DistFile)>> {
+        let (task, content_hash) = match entry {
+            SnapshotEntry::Page {
+                task, content_hash, ..
+            } => (task, content_hash),
+            SnapshotEntry::HashAsset { task } => {
+                // Content-addressed, so the same path means the same content.
+                let change = match baseline.contains(path, dist) {
+                    true => Change::Unchanged,
+                    false => Change::New,
+                };
+                return Ok(Some((change, DistFile::new(path, task))));
+            }
+            SnapshotEntry::StaticFile { .. } => return Ok(None),
+        };
+
+        let abs_path = dist.join(path);
+        let prev_hash = match baseline {
+            Baseline::Disk => match Hash32::hash_file(&abs_path) {
+                Ok(hash) => Some(Some(hash)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            },
+            #[cfg(any(test, feature = "live"))]
+            Baseline::Snapshot(prev) => prev.entries.get(path).map(|entry| match entry {
+                SnapshotEntry::Page { content_hash, .. } => Some(*content_hash),
+                _ => None,
+            }),
+            Baseline::Meta(prev) => prev.entries.get(path.as_str()).map(|entry| match entry {
+                MetaEntry::Page { content_hash } => Some(Hash32::from(*content_hash)),
+                _ => None,
+            }),
+        };
+
+        let change = match prev_hash {
+            None => {
+                tracing::debug!("new page: {}", path);
+                Change::New
+            }
+            Some(Some(prev_hash)) if prev_hash == *content_hash && abs_path.exists() => {
+                tracing::debug!("unchanged page, skipping: {}", path);
+                Change::Unchanged
+            }
+            Some(Some(_)) => {
+                tracing::debug!("changed or missing page: {}", path);
+                Change::Changed
+            }
+            Some(None) => {
+                tracing::debug!("new page (replaced non-page entry): {}", path);
+                Change::Changed
+            }
+        };
+
+        Ok(Some((change, DistFile::new(path, task))))
+    }
+
+    /// Applies a report computed by [`diff`](Self::diff) to `dist`.
+    ///
+    /// 1. Deletes the removed files and prunes the directories left empty.
+    /// 2. Writes the new and changed pages in parallel.
+    fn apply(&self, report: &CommitReport, dist: &camino::Utf8Path) -> io::Result<()> {
+        let dist = dist.as_std_path();
+        fs::create_dir_all(dist)?;
+
+        let mut removed = 0;
+        let mut dirs_to_prune: HashSet<std::path::PathBuf> = HashSet::new();
+        for DistFile { path, .. } in &report.removed {
+            let abs = dist.join(path.as_std_path());
+            tracing::debug!("removing stale dist file: {}", path);
+            match fs::remove_file(&abs) {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    tracing::debug!("stale file already gone: {}", path);
                 }
+                Err(e) => return Err(e),
+            }
+            if let Some(parent) = abs.parent() {
+                dirs_to_prune.insert(parent.to_path_buf());
             }
         }
         if removed > 0 {
@@ -364,53 +483,303 @@ impl Snapshot {
             prune_empty_dirs(dist, dirs_to_prune)?;
         }
 
-        // Write pages that are new or whose content hash changed.
         write_pages(
             dist,
-            self.entries.iter().filter_map(|(path, entry)| {
-                let SnapshotEntry::Page {
-                    output,
-                    content_hash,
-                    ..
-                } = entry
-                else {
-                    return None;
-                };
-                match prev.entries.get(path.as_str()) {
-                    None => {
-                        tracing::debug!("new page: {}", path);
-                        Some((path, output, content_hash))
-                    }
-                    Some(MetaEntry::Page {
-                        content_hash: prev_hash,
-                    }) => {
-                        let abs_path = dist.join(path.as_std_path());
-                        if prev_hash != &content_hash.to_bytes() || !abs_path.exists() {
-                            tracing::debug!("changed or missing page: {}", path);
-                            Some((path, output, content_hash))
-                        } else {
-                            tracing::debug!("unchanged page, skipping: {}", path);
-                            None
+            report
+                .written()
+                .filter_map(|file| match self.entries.get(&file.path) {
+                    Some(SnapshotEntry::Page { output, .. }) => Some((&file.path, output)),
+                    _ => None,
+                }),
+        )
+    }
+}
+
+/// What a snapshot is compared against by [`Snapshot::diff`].
+pub(crate) enum Baseline<'a> {
+    /// The files currently in `dist`, found by walking it.
+    Disk,
+    /// The snapshot of the previous build, kept in memory while watching.
+    #[cfg(any(test, feature = "live"))]
+    Snapshot(&'a Snapshot),
+    /// The snapshot of the previous build, loaded from the cache directory.
+    Meta(&'a SnapshotMeta),
+}
+
+/// How an entry of a snapshot differs from the [`Baseline`].
+enum Change {
+    New,
+    Changed,
+    Unchanged,
+}
+
+impl Baseline<'_> {
+    /// Returns `true` if the previous build produced a file at `path`, or
+    /// without a previous build, if the file exists in `dist`.
+    fn contains(&self, path: &Utf8Path, dist: &Utf8Path) -> bool {
+        match self {
+            Baseline::Disk => dist.join(path).is_file(),
+            #[cfg(any(test, feature = "live"))]
+            Baseline::Snapshot(prev) => prev.entries.contains_key(path),
+            Baseline::Meta(prev) => prev.entries.contains_key(path.as_str()),
+        }
+    }
+}
+
+impl Snapshot {
+    /// Converts this snapshot into a [`BuiltSite`] held in memory. Hash
+    /// assets are read back from the cache directory, where they are staged,
+    /// and static files from their source in `vfs`.
+    pub(crate) fn into_built(
+        self,
+        dist: &camino::Utf8Path,
+        cache_dir: &camino::Utf8Path,
+        vfs: &dyn Vfs,
+    ) -> io::Result<BuiltSite> {
+        let files = self
+            .entries
+            .into_par_iter()
+            .map(|(path, entry)| {
+                let file = match entry {
+                    SnapshotEntry::Page { task, output, .. } => BuiltFile {
+                        data: output.data,
+                        producer: Producer::Page { task },
+                    },
+                    SnapshotEntry::HashAsset { task } => {
+                        let source = crate::engine::cached_store_path(cache_dir, &path)
+                            .unwrap_or_else(|| dist.join(&path));
+                        BuiltFile {
+                            data: OutputData::Binary(fs::read(source)?),
+                            producer: Producer::Asset { task },
                         }
                     }
-                    _ => {
-                        tracing::debug!("new page (replaced non-page entry): {}", path);
-                        Some((path, output, content_hash))
-                    }
-                }
-            }),
+                    SnapshotEntry::StaticFile { source } => BuiltFile {
+                        data: OutputData::Binary(vfs.read(&source)?.into()),
+                        producer: Producer::Static { source },
+                    },
+                };
+                Ok((path, file))
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(BuiltSite { files })
+    }
+}
+
+impl SnapshotEntry {
+    /// Name of the task that produced this entry, `None` for static files.
+    fn task(&self) -> Option<&str> {
+        match self {
+            SnapshotEntry::Page { task, .. } | SnapshotEntry::HashAsset { task } => Some(task),
+            SnapshotEntry::StaticFile { .. } => None,
+        }
+    }
+}
+
+/// A read-only view of the files a build is about to write to `dist`.
+///
+/// Passed to the hooks registered with
+/// [`Blueprint::on_after_tasks`](crate::Blueprint::on_after_tasks).
+pub struct SnapshotView<'a> {
+    snapshot: &'a Snapshot,
+}
+
+impl<'a> SnapshotView<'a> {
+    pub(crate) fn new(snapshot: &'a Snapshot) -> Self {
+        Self { snapshot }
+    }
+
+    /// Returns the page that will be written to the dist-relative `path`.
+    pub fn page(&self, path: impl AsRef<Utf8Path>) -> Option<&'a Output> {
+        match self.snapshot.entries.get(path.as_ref()) {
+            Some(SnapshotEntry::Page { output, .. }) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Iterates over every page produced by the tasks, in no particular order.
+    pub fn pages(&self) -> impl Iterator<Item = &'a Output> {
+        self.snapshot
+            .entries
+            .values()
+            .filter_map(|entry| match entry {
+                SnapshotEntry::Page { output, .. } => Some(output),
+                _ => None,
+            })
+    }
+
+    /// Iterates over the dist-relative paths of every file in the snapshot,
+    /// including content-addressed assets and static files.
+    pub fn paths(&self) -> impl Iterator<Item = &'a Utf8Path> {
+        self.snapshot.entries.keys().map(Utf8PathBuf::as_path)
+    }
+
+    /// Number of pages produced by the tasks.
+    pub fn page_count(&self) -> usize {
+        self.snapshot.page_count()
+    }
+}
+
+/// The changes a commit made to `dist`.
+///
+/// Covers the files produced by tasks, static files copied with
+/// [`Blueprint::copy_static`](crate::Blueprint::copy_static) only show up
+/// when they are removed. Returned as part of the
+/// [`Diagnostics`](crate::Diagnostics) and passed to the hooks registered
+/// with [`Blueprint::on_after_commit`](crate::Blueprint::on_after_commit).
+#[derive(Debug, Clone, Default)]
+pub struct CommitReport {
+    /// Files that did not exist in the previous build.
+    pub new: Vec<DistFile>,
+    /// Files whose content changed since the previous build.
+    pub changed: Vec<DistFile>,
+    /// Files whose content is the same as in the previous build.
+    pub unchanged: Vec<DistFile>,
+    /// Files removed from `dist`.
+    pub removed: Vec<DistFile>,
+}
+
+impl CommitReport {
+    /// Iterates over the files that were new or changed, i.e. the URLs whose
+    /// content differs from the previous deployment.
+    pub fn written(&self) -> impl Iterator<Item = &DistFile> {
+        self.new.iter().chain(&self.changed)
+    }
+
+    /// Returns `true` if the commit did not modify `dist`.
+    pub fn is_empty(&self) -> bool {
+        self.new.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl Display for CommitReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} new, {} changed, {} unchanged, {} removed",
+            self.new.len(),
+            self.changed.len(),
+            self.unchanged.len(),
+            self.removed.len()
         )
     }
 }
 
+/// A single file in a [`CommitReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistFile {
+    /// Path relative to `dist`.
+    pub path: Utf8PathBuf,
+    /// Name of the task that produced the file, `None` if it is not known,
+    /// e.g. for static files and for stale files found by walking `dist`.
+    pub task: Option<String>,
+}
+
+impl DistFile {
+    fn new(path: &Utf8Path, task: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+            task: Some(task.to_owned()),
+        }
+    }
+}
+
+/// Every file produced by a build, held in memory.
+///
+/// Returned by [`Website::build_in_memory`](crate::Website::build_in_memory).
+/// Paths are relative to `dist`, a leading `/` is ignored on lookup so URLs
+/// can be used directly.
+#[derive(Debug, Clone, Default)]
+pub struct BuiltSite {
+    files: BTreeMap<Utf8PathBuf, BuiltFile>,
+}
+
+impl BuiltSite {
+    /// Returns the file at `path`.
+    pub fn get(&self, path: impl AsRef<Utf8Path>) -> Option<&BuiltFile> {
+        let path = path.as_ref().as_str().trim_start_matches('/');
+        self.files.get(Utf8Path::new(path))
+    }
+
+    /// Returns the content of the file at `path`.
+    pub fn bytes(&self, path: impl AsRef<Utf8Path>) -> Option<&[u8]> {
+        self.get(path).map(|file| file.data.as_ref())
+    }
+
+    /// Returns the content of the file at `path`, if it is valid UTF-8.
+    pub fn text(&self, path: impl AsRef<Utf8Path>) -> Option<&str> {
+        match &self.get(path)?.data {
+            OutputData::Utf8(text) => Some(text),
+            OutputData::Binary(data) => std::str::from_utf8(data).ok(),
+        }
+    }
+
+    /// Iterates over all files, sorted by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&Utf8Path, &BuiltFile)> {
+        self.files.iter().map(|(path, file)| (path.as_path(), file))
+    }
+
+    /// Iterates over the paths of all files, sorted.
+    pub fn paths(&self) -> impl Iterator<Item = &Utf8Path> {
+        self.files.keys().map(Utf8PathBuf::as_path)
+    }
+
+    /// Number of files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns `true` if the build produced no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// A single file in a [`BuiltSite`].
+#[derive(Debug, Clone)]
+pub struct BuiltFile {
+    /// The content of the file.
+    pub data: OutputData,
+    /// Where the file came from.
+    pub producer: Producer,
+}
+
+/// What produced a file in `dist`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Producer {
+    /// An [`Output`] returned by a task.
+    Page { task: String },
+    /// A content-addressed asset saved with [`Store::save`](crate::Store::save).
+    Asset { task: String },
+    /// A file copied with [`Blueprint::copy_static`](crate::Blueprint::copy_static).
+    Static { source: Utf8PathBuf },
+}
+
+impl Producer {
+    /// Name of the task that produced the file, `None` for static files.
+    pub fn task(&self) -> Option<&str> {
+        match self {
+            Producer::Page { task } | Producer::Asset { task } => Some(task),
+            Producer::Static { .. } => None,
+        }
+    }
+}
+
 /// Slim, serializable representation of a [`Snapshot`].
 ///
 /// Stored at `.cache/snapshot/metadata.cbor` after each successful build.
 /// Loaded on the next cold start to drive [`Snapshot::commit_diff_meta`],
 /// skipping unchanged pages without a full `dist` walk.
-#[derive(Serialize, Deserialize)]
+#[derive(Default, Serialize, Deserialize)]
 pub(crate) struct SnapshotMeta {
     entries: HashMap<String, MetaEntry>,
+    /// Task that produced each entry, reported when the entry is removed.
+    #[serde(default)]
+    producers: HashMap<String, String>,
+    /// Index of the task that produced each page in the build graph, only
+    /// meaningful as long as the graph didn't change.
+    #[serde(default)]
+    nodes: HashMap<String, usize>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -440,6 +809,32 @@ impl SnapshotMeta {
             .map_err(io::Error::other)
     }
 
+    /// Returns the tasks that produced the pages selected by `filter` in the
+    /// build this snapshot was taken from, by their index in the build graph
+    /// and their name.
+    pub(crate) fn producers_of(&self, filter: &OutputFilter) -> Vec<(NodeIndex, &str)> {
+        self.nodes
+            .iter()
+            .filter(|(path, _)| filter.matches(Utf8Path::new(path)))
+            .filter_map(|(path, &node)| {
+                Some((NodeIndex::new(node), self.producers.get(path)?.as_str()))
+            })
+            .collect()
+    }
+
+    /// Merges the snapshot of a filtered build into this one, after the
+    /// `removed` files were deleted from `dist`.
+    pub(crate) fn update(&mut self, next: SnapshotMeta, removed: &[DistFile]) {
+        for file in removed {
+            self.entries.remove(file.path.as_str());
+            self.producers.remove(file.path.as_str());
+            self.nodes.remove(file.path.as_str());
+        }
+        self.entries.extend(next.entries);
+        self.producers.extend(next.producers);
+        self.nodes.extend(next.nodes);
+    }
+
     /// Persists this snapshot meta to `{cache_dir}/snapshot/metadata.cbor`.
     pub(crate) fn save(&self, cache_dir: &camino::Utf8Path) -> io::Result<()> {
         let path = cache_dir.join(Self::RELATIVE_PATH);
@@ -456,13 +851,13 @@ impl SnapshotMeta {
 /// so workers never race on directory creation.
 fn write_pages<'a>(
     dist: &Path,
-    pages: impl Iterator<Item = (&'a Utf8PathBuf, &'a Output, &'a Hash32)>,
+    pages: impl Iterator<Item = (&'a Utf8PathBuf, &'a Output)>,
 ) -> io::Result<()> {
     let pages: Vec<_> = pages.collect();
 
     let parent_dirs: HashSet<std::path::PathBuf> = pages
         .iter()
-        .filter_map(|(path, _, _)| {
+        .filter_map(|(path, _)| {
             dist.join(path.as_std_path())
                 .parent()
                 .map(|p| p.to_path_buf())
@@ -472,34 +867,42 @@ fn write_pages<'a>(
         fs::create_dir_all(dir)?;
     }
 
-    pages.par_iter().try_for_each(|(path, output, _hash)| {
-        fs::write(dist.join(path.as_std_path()), &output.data)
-    })
+    pages
+        .par_iter()
+        .try_for_each(|(path, output)| fs::write(dist.join(path.as_std_path()), &output.data))
 }
 
 /// Removes empty directories left after deletions.
 ///
 /// Sorts candidates deepest-first so a parent is only attempted after all
-/// its children have been processed. `fs::remove_dir` is a no-op on
-/// non-empty directories - errors are silently ignored.
+/// its children have been processed, parents emptied by the removal of a
+/// child are pruned as well. `fs::remove_dir` is a no-op on non-empty
+/// directories - errors are silently ignored.
 fn prune_empty_dirs(dist: &Path, dirs: HashSet<std::path::PathBuf>) -> io::Result<()> {
     let mut dirs: Vec<_> = dirs.into_iter().collect();
     dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
     for dir in dirs {
-        if dir == dist {
-            continue;
-        }
-        if fs::remove_dir(&dir).is_ok() {
+        for dir in dir
+            .ancestors()
+            .take_while(|dir| *dir != dist && dir.starts_with(dist))
+        {
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
             tracing::debug!("pruned empty dir: {}", dir.display());
         }
     }
     Ok(())
 }
 
-/// Walks `dist/<rel>` and removes any file whose dist-relative path is not in `desired`.
-/// Empty directories left behind after removal are pruned as well.
-/// Returns the number of files deleted.
-fn remove_stale(dist: &Path, rel: &Utf8Path, desired: &HashSet<Utf8PathBuf>) -> io::Result<usize> {
+/// Walks `dist/<rel>` and collects every file whose dist-relative path is
+/// not in `desired` into `stale`, without removing anything.
+fn find_stale(
+    dist: &Path,
+    rel: &Utf8Path,
+    desired: &HashSet<Utf8PathBuf>,
+    stale: &mut Vec<Utf8PathBuf>,
+) -> io::Result<()> {
     let dir = if rel.as_str().is_empty() {
         dist.to_path_buf()
     } else {
@@ -509,12 +912,10 @@ fn remove_stale(dist: &Path, rel: &Utf8Path, desired: &HashSet<Utf8PathBuf>) ->
     let read_dir = match fs::read_dir(&dir) {
         Ok(rd) => rd,
         // Nothing to sweep if dist doesn't exist yet (first build).
-        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
         Err(e) => return Err(e),
     };
 
-    let mut removed = 0;
-
     for entry in read_dir {
         let entry = entry?;
         let name = entry.file_name();
@@ -528,25 +929,21 @@ fn remove_stale(dist: &Path, rel: &Utf8Path, desired: &HashSet<Utf8PathBuf>) ->
             rel.join(name_str)
         };
 
-        let entry_abs = dist.join(entry_rel.as_std_path());
         if entry.file_type()?.is_dir() {
-            removed += remove_stale(dist, &entry_rel, desired)?;
-            if fs::read_dir(&entry_abs)?.next().is_none() {
-                fs::remove_dir(&entry_abs)?;
-            }
+            find_stale(dist, &entry_rel, desired, stale)?;
         } else if !desired.contains(entry_rel.as_path()) {
-            tracing::debug!("removing stale dist file: {}", entry_rel);
-            fs::remove_file(&entry_abs)?;
-            removed += 1;
+            stale.push(entry_rel);
         }
     }
 
-    Ok(removed)
+    Ok(())
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
+    use crate::test_utils::TempDir;
     use crate::{Output, output::OutputData};
 
     #[test]
@@ -609,4 +1006,55 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn reports_changes_between_snapshots() {
+        let dist = TempDir::new("commit");
+
+        let mut prev = Snapshot::new();
+        for (path, data) in [("a.html", "a"), ("b.html", "b"), ("c.html", "c")] {
+            prev.insert_page(NodeIndex::new(0), "prev", Output::binary(path, data))
+                .unwrap();
+        }
+        prev.commit(&dist).unwrap();
+
+        let mut next = Snapshot::new();
+        for (path, data) in [("a.html", "a"), ("b.html", "changed"), ("d.html", "d")] {
+            next.insert_page(NodeIndex::new(0), "next", Output::binary(path, data))
+                .unwrap();
+        }
+        let report = next.commit_diff(&prev, &dist).unwrap();
+
+        let paths = |files: &[DistFile]| -> Vec<String> {
+            files.iter().map(|file| file.path.to_string()).collect()
+        };
+        assert_eq!(paths(&report.new), ["d.html"]);
+        assert_eq!(paths(&report.changed), ["b.html"]);
+        assert_eq!(paths(&report.unchanged), ["a.html"]);
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].path, "c.html");
+        assert_eq!(report.removed[0].task.as_deref(), Some("prev"));
+        assert_eq!(report.new[0].task.as_deref(), Some("next"));
+    }
+
+    #[test]
+    fn reports_existing_hash_assets_without_baseline() {
+        let dist = TempDir::new("assets");
+        fs::create_dir_all(dist.join("hash")).unwrap();
+        fs::write(dist.join("hash/old.css"), "").unwrap();
+
+        let mut snapshot = Snapshot::new();
+        for path in ["hash/old.css", "hash/new.css"] {
+            snapshot
+                .insert_hash_asset(NodeIndex::new(0), "css", path.into())
+                .unwrap();
+        }
+        let report = snapshot.diff(Baseline::Disk, &dist).unwrap();
+
+        assert_eq!(report.new, [DistFile::new("hash/new.css".into(), "css")]);
+        assert_eq!(
+            report.unchanged,
+            [DistFile::new("hash/old.css".into(), "css")]
+        );
+    }
 }
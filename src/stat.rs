@@ -0,0 +1,188 @@
+//! Persistent cache of file content hashes.
+//!
+//! Loaders identify their input files by content hash, which means reading
+//! every matched file on every run. The [`StatCache`] remembers the hash along
+//! with the file's size, modification time and inode, and only hashes the file
+//! again when any of them changed, similar to the mtime shortcut used when
+//! copying static files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::core::Hash32;
+use crate::vfs::Vfs;
+
+/// Files modified more recently than this are always hashed and never cached,
+/// because a write within the same mtime tick would go unnoticed.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stat {
+    size: u64,
+    mtime: SystemTime,
+    inode: u64,
+}
+
+impl Stat {
+    fn new(meta: &fs::Metadata) -> io::Result<Self> {
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(meta);
+        #[cfg(not(unix))]
+        let inode = 0;
+
+        Ok(Self {
+            size: meta.len(),
+            mtime: meta.modified()?,
+            inode,
+        })
+    }
+
+    fn is_racy(&self) -> bool {
+        SystemTime::now()
+            .duration_since(self.mtime)
+            .map_or(true, |age| age < RACY_WINDOW)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct StatEntry {
+    stat: Stat,
+    hash: Hash32,
+}
+
+/// Maps file paths to their content hashes, keyed by stat data.
+#[derive(Default)]
+pub(crate) struct StatCache {
+    /// Entries loaded from the previous run.
+    old: HashMap<Utf8PathBuf, StatEntry>,
+    /// Entries used during this run, only these are saved back to disk so
+    /// that deleted files don't accumulate.
+    new: Mutex<HashMap<Utf8PathBuf, StatEntry>>,
+}
+
+impl StatCache {
+    const RELATIVE_PATH: &'static str = "stat/metadata.cbor";
+
+    /// Loads the persisted stat cache from `{cache_dir}/stat/metadata.cbor`.
+    ///
+    /// A missing or unreadable cache results in an empty one, every file is
+    /// then simply hashed again.
+    pub(crate) fn load(cache_dir: &Utf8Path) -> Self {
+        let path = cache_dir.join(Self::RELATIVE_PATH);
+        let old = match fs::File::open(&path) {
+            Ok(file) => ciborium::from_reader(io::BufReader::new(file)).unwrap_or_else(|e| {
+                tracing::warn!("failed to read stat cache {}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            old,
+            new: Mutex::default(),
+        }
+    }
+
+    /// Persists all entries used during this run to `{cache_dir}/stat/metadata.cbor`.
+    pub(crate) fn save(&self, cache_dir: &Utf8Path) -> io::Result<()> {
+        #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+        let new = self.new.lock().unwrap();
+        fs::create_dir_all(cache_dir.join("stat"))?;
+        let file = fs::File::create(cache_dir.join(Self::RELATIVE_PATH))?;
+        ciborium::into_writer(&*new, io::BufWriter::new(file)).map_err(io::Error::other)
+    }
+
+    /// Returns the content hash of a file, hashing it only if its stat data
+    /// changed since it was last seen. Files which aren't on the local disk
+    /// are always hashed.
+    pub(crate) fn hash_file(&self, vfs: &dyn Vfs, path: &Utf8Path) -> io::Result<Hash32> {
+        let Some(path) = vfs.local_path(path) else {
+            return vfs.hash(path).map(Hash32::from);
+        };
+        let path = path.as_path();
+
+        let stat = Stat::new(&fs::metadata(path)?)?;
+
+        {
+            #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+            let mut new = self.new.lock().unwrap();
+            if let Some(entry) = new.get(path)
+                && entry.stat == stat
+            {
+                return Ok(entry.hash);
+            }
+            // Entries from the previous run are carried over, so that they
+            // are saved again.
+            if let Some(&entry) = self.old.get(path)
+                && entry.stat == stat
+            {
+                new.insert(path.to_owned(), entry);
+                return Ok(entry.hash);
+            }
+        }
+
+        let hash = Hash32::hash_file(path)?;
+
+        if !stat.is_racy() {
+            #[allow(clippy::unwrap_used)] // poisoned mutex means a thread panicked - unrecoverable
+            self.new
+                .lock()
+                .unwrap()
+                .insert(path.to_owned(), StatEntry { stat, hash });
+        }
+
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use crate::vfs::Disk;
+
+    #[test]
+    fn test_stat_cache_roundtrip() {
+        let dir = TempDir::new("stat");
+
+        let file = dir.join("old.txt");
+        fs::write(&file, "content").unwrap();
+        let mtime = SystemTime::now() - Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let cache = StatCache::load(&dir);
+        let hash = cache.hash_file(&Disk, &file).unwrap();
+        assert_eq!(hash, Hash32::hash(b"content"));
+        cache.save(&dir).unwrap();
+
+        let cache = StatCache::load(&dir);
+        assert_eq!(cache.old[&file].hash, hash);
+
+        // a hit from the previous run is saved again
+        assert_eq!(cache.hash_file(&Disk, &file).unwrap(), hash);
+        cache.save(&dir).unwrap();
+        let cache = StatCache::load(&dir);
+        assert_eq!(cache.old[&file].hash, hash);
+
+        // a recently modified file is hashed but not remembered
+        let recent = dir.join("recent.txt");
+        fs::write(&recent, "recent").unwrap();
+        assert_eq!(
+            cache.hash_file(&Disk, &recent).unwrap(),
+            Hash32::hash(b"recent")
+        );
+        assert!(!cache.new.lock().unwrap().contains_key(&recent));
+    }
+}
@@ -0,0 +1,71 @@
+//! Helpers shared by the unit tests.
+
+use std::fs;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::core::{ImportMap, Mode};
+use crate::{Environment, TaskContext};
+
+/// Environment of a production build.
+pub(crate) const ENV: Environment = Environment {
+    generator: "test",
+    mode: Mode::Build,
+    port: None,
+    data: (),
+};
+
+/// Context for tests that execute a task directly, outside of a build.
+pub(crate) fn make_ctx() -> TaskContext<'static, ()> {
+    TaskContext {
+        env: &ENV,
+        importmap: Box::leak(Box::new(ImportMap::new())),
+        span: tracing::Span::none(),
+        progress: Box::leak(Box::new(crate::utils::ProgressStyles::default())),
+        continue_on_error: false,
+        cancel: crate::engine::CancelToken::default(),
+    }
+}
+
+/// An empty temporary directory, removed again when it's dropped.
+pub(crate) struct TempDir {
+    path: Utf8PathBuf,
+}
+
+impl TempDir {
+    /// Creates the directory, `name` only makes it easier to recognize, the
+    /// path is unique to each call.
+    pub(crate) fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "hauchiwa-{name}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let path = Utf8PathBuf::try_from(path).unwrap();
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub(crate) fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Utf8Path;
+
+    fn deref(&self) -> &Utf8Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
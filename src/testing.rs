@@ -0,0 +1,604 @@
+//! Helpers for testing sites built with hauchiwa, behind the `testing` feature.
+//!
+//! A [`Fixture`] builds a [`Website`] inside a temporary copy of a fixture
+//! directory, without writing anything to `dist`. The output can be compared
+//! with a checked-in tree using [`assert_golden`], and the files of the
+//! fixture can be edited to check which tasks run again in an incremental
+//! rebuild.
+//!
+//! Expected trees are written from the current output instead of compared
+//! when the `HAUCHIWA_BLESS` environment variable is set, e.g.
+//! `HAUCHIWA_BLESS=1 cargo test`.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use hauchiwa::testing::{Fixture, assert_golden};
+//! use hauchiwa::{Blueprint, Output};
+//!
+//! let mut fixture = Fixture::new("tests/fixtures/blog", (), |root| {
+//!     let mut config = Blueprint::<()>::new();
+//!     let posts = config
+//!         .task()
+//!         .name("posts")
+//!         .glob(format!("{root}/posts/*.md"))?
+//!         .map(|_, _, input| Ok(String::from_utf8(input.read()?.into())?));
+//!     config
+//!         .task()
+//!         .name("index")
+//!         .using(posts)
+//!         .merge(|_, posts| Ok(Output::html("index", posts.values().cloned().collect::<String>())));
+//!     Ok(config)
+//! })?;
+//! assert_golden(fixture.site(), "tests/fixtures/blog.expected");
+//!
+//! fixture.write("posts/hello.md", "# Hello again")?;
+//! fixture.rebuild()?.assert_executed(["posts", "index"]);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use petgraph::graph::NodeIndex;
+
+use crate::engine::{
+    CancelToken, NodeData, collect_manifest, find_affected, find_dirty, run_once_parallel,
+    run_tasks_parallel,
+};
+use crate::error::{BuildError, HauchiwaError};
+use crate::snapshot::Snapshot;
+use crate::{Blueprint, BuiltSite, Diagnostics, Environment, Mode, Website};
+
+/// Environment variable that makes [`assert_golden`] overwrite the expected
+/// tree instead of comparing against it.
+pub const BLESS_VAR: &str = "HAUCHIWA_BLESS";
+
+/// Lines of unchanged text shown around each difference.
+const CONTEXT: usize = 3;
+
+/// A website built from a temporary copy of a fixture directory.
+///
+/// The copy is removed when the fixture is dropped.
+pub struct Fixture<G: Send + Sync = ()> {
+    site: Website<G>,
+    globals: Environment<G>,
+    cache: HashMap<NodeIndex, NodeData>,
+    built: BuiltSite,
+    changed: BTreeSet<Utf8PathBuf>,
+    root: TempRoot,
+}
+
+/// A temporary directory, removed when it's dropped. Created before the
+/// fixture is copied into it, so that it's also removed when copying,
+/// `setup` or the first build fail.
+struct TempRoot(Utf8PathBuf);
+
+impl Drop for TempRoot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+impl<G: Send + Sync + 'static> Fixture<G> {
+    /// Copies `fixture` to a new temporary directory and builds the website
+    /// configured by `setup` in it.
+    ///
+    /// `setup` receives the path of the copy, the globs of the tasks and the
+    /// static directories should be rooted there. The `dist` and cache
+    /// directories are placed inside the copy as well.
+    pub fn new<F>(fixture: impl AsRef<Utf8Path>, data: G, setup: F) -> Result<Self, HauchiwaError>
+    where
+        F: FnOnce(&Utf8Path) -> Result<Blueprint<G>, HauchiwaError>,
+    {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let root = std::env::temp_dir().join(format!(
+            "hauchiwa-fixture-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let root = Utf8PathBuf::try_from(root).map_err(|e| BuildError::Other(e.into()))?;
+        let _ = fs::remove_dir_all(&root);
+        let root = TempRoot(root);
+        copy_dir(fixture.as_ref(), &root.0).map_err(BuildError::Io)?;
+
+        let mut site = setup(&root.0)?
+            .set_dir_dist(root.0.join("dist"))
+            .set_dir_cache(root.0.join(".cache"))
+            .finish();
+        site.staging = true;
+        site.run_preflight()?;
+
+        let globals = Environment {
+            generator: "hauchiwa",
+            mode: Mode::Build,
+            port: None,
+            data,
+        };
+
+        let (cache, snapshot, diagnostics) = run_once_parallel(&mut site, &globals)?;
+        if !diagnostics.failures.is_empty() {
+            return Err(BuildError::Tasks(diagnostics.failures).into());
+        }
+
+        let built = collect(&site, snapshot)?;
+
+        Ok(Self {
+            site,
+            globals,
+            cache,
+            built,
+            changed: BTreeSet::new(),
+            root,
+        })
+    }
+
+    /// Path of the temporary copy of the fixture.
+    pub fn root(&self) -> &Utf8Path {
+        &self.root.0
+    }
+
+    /// The output of the most recent build.
+    pub fn site(&self) -> &BuiltSite {
+        &self.built
+    }
+
+    /// Writes a file of the fixture, `path` is relative to [`root`](Self::root).
+    /// The change is picked up by the next [`rebuild`](Self::rebuild).
+    pub fn write(
+        &mut self,
+        path: impl AsRef<Utf8Path>,
+        contents: impl AsRef<[u8]>,
+    ) -> io::Result<()> {
+        let path = self.root.0.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents)?;
+        self.changed.insert(path);
+        Ok(())
+    }
+
+    /// Removes a file of the fixture, `path` is relative to [`root`](Self::root).
+    /// The change is picked up by the next [`rebuild`](Self::rebuild).
+    pub fn remove(&mut self, path: impl AsRef<Utf8Path>) -> io::Result<()> {
+        let path = self.root.0.join(path);
+        fs::remove_file(&path)?;
+        self.changed.insert(path);
+        Ok(())
+    }
+
+    /// Rebuilds the website incrementally after files were changed, the same
+    /// way watch mode does.
+    pub fn rebuild(&mut self) -> Result<Rebuild, HauchiwaError> {
+        let mut dirty = HashSet::new();
+        for path in std::mem::take(&mut self.changed) {
+            dirty.extend(find_dirty(&self.site, &path));
+        }
+        let affected = find_affected(&self.site, &dirty);
+
+        let diagnostics = run_tasks_parallel(
+            &self.site,
+            &self.globals,
+            &mut self.cache,
+            &affected,
+            &dirty,
+            &CancelToken::new(),
+        )?;
+        if !diagnostics.failures.is_empty() {
+            return Err(BuildError::Tasks(diagnostics.failures).into());
+        }
+
+        let snapshot = collect_manifest(&self.cache, &self.site.graph)?;
+        self.built = collect(&self.site, snapshot)?;
+
+        let executed = diagnostics
+            .execution_times
+            .iter()
+            .filter(|(_, execution)| execution.executed)
+            .map(|(&index, _)| self.site.graph[index].name())
+            .collect();
+
+        Ok(Rebuild {
+            executed,
+            diagnostics,
+        })
+    }
+}
+
+/// The result of [`Fixture::rebuild`].
+#[derive(Debug)]
+pub struct Rebuild {
+    executed: BTreeSet<String>,
+    /// Diagnostics of the tasks that were considered for the rebuild.
+    pub diagnostics: Diagnostics,
+}
+
+impl Rebuild {
+    /// Names of the tasks that ran again, sorted.
+    pub fn executed(&self) -> impl Iterator<Item = &str> {
+        self.executed.iter().map(String::as_str)
+    }
+
+    /// Asserts that exactly the tasks named in `expected` ran again.
+    #[track_caller]
+    pub fn assert_executed<'a>(&self, expected: impl IntoIterator<Item = &'a str>) {
+        let expected: BTreeSet<&str> = expected.into_iter().collect();
+        let executed: BTreeSet<&str> = self.executed().collect();
+
+        #[allow(clippy::panic)] // reporting a failed assertion is the purpose
+        if expected != executed {
+            panic!(
+                "unexpected tasks ran in the rebuild\n  expected: {:?}\n  executed: {:?}",
+                expected, executed
+            );
+        }
+    }
+}
+
+/// Compares `site` with the files under the `expected` directory, and panics
+/// with a diff of every file that is missing, unexpected or different.
+///
+/// If the [`BLESS_VAR`] environment variable is set, the `expected` directory
+/// is replaced with the contents of `site` instead.
+#[track_caller]
+pub fn assert_golden(site: &BuiltSite, expected: impl AsRef<Utf8Path>) {
+    let expected = expected.as_ref();
+
+    #[allow(clippy::panic)] // reporting a failed assertion is the purpose
+    if std::env::var_os(BLESS_VAR).is_some() {
+        if let Err(e) = bless(site, expected) {
+            panic!("failed to write expected tree `{expected}`: {e}");
+        }
+    } else {
+        match compare(site, expected) {
+            Ok(None) => {}
+            Ok(Some(report)) => panic!(
+                "output differs from `{expected}`, set {BLESS_VAR}=1 to accept it\n\n{report}"
+            ),
+            Err(e) => panic!("failed to read expected tree `{expected}`: {e}"),
+        }
+    }
+}
+
+/// Converts a snapshot into a [`BuiltSite`], together with the static files.
+fn collect<G: Send + Sync>(
+    site: &Website<G>,
+    mut snapshot: Snapshot,
+) -> Result<BuiltSite, HauchiwaError> {
+    for entry in crate::utils::collect_static(&site.copied, &site.out_dir, &*site.vfs)? {
+        snapshot.insert_static_file(entry.dist_rel, entry.source)?;
+    }
+
+    let built = snapshot
+        .into_built(&site.out_dir, &site.cache_dir, &*site.vfs)
+        .map_err(BuildError::Io)?;
+
+    Ok(built)
+}
+
+fn bless(site: &BuiltSite, expected: &Utf8Path) -> io::Result<()> {
+    match fs::remove_dir_all(expected) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    for (path, file) in site.iter() {
+        let path = expected.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, &file.data)?;
+    }
+
+    Ok(())
+}
+
+/// Returns a readable report of the differences, `None` if there are none.
+fn compare(site: &BuiltSite, expected: &Utf8Path) -> io::Result<Option<String>> {
+    let mut files = BTreeMap::new();
+    read_tree(expected, Utf8Path::new(""), &mut files)?;
+
+    let mut report = String::new();
+    for (path, file) in site.iter() {
+        let actual = file.data.as_ref();
+        match files.remove(path) {
+            None => {
+                let _ = writeln!(report, "+++ {path} (unexpected file)");
+                if let Ok(text) = std::str::from_utf8(actual) {
+                    diff_lines(&mut report, "", text);
+                }
+            }
+            Some(expected) if expected != actual => {
+                let _ = writeln!(report, "~~~ {path}");
+                match (std::str::from_utf8(&expected), std::str::from_utf8(actual)) {
+                    (Ok(expected), Ok(actual)) => diff_lines(&mut report, expected, actual),
+                    _ => {
+                        let _ = writeln!(
+                            report,
+                            "binary files differ ({} bytes expected, {} bytes actual)",
+                            expected.len(),
+                            actual.len()
+                        );
+                    }
+                }
+            }
+            Some(_) => continue,
+        }
+        report.push('\n');
+    }
+    for path in files.keys() {
+        let _ = writeln!(report, "--- {path} (missing file)\n");
+    }
+
+    Ok((!report.is_empty()).then_some(report))
+}
+
+fn read_tree(
+    root: &Utf8Path,
+    rel: &Utf8Path,
+    files: &mut BTreeMap<Utf8PathBuf, Vec<u8>>,
+) -> io::Result<()> {
+    let dir = root.join(rel);
+    let read_dir = match fs::read_dir(&dir) {
+        Ok(read_dir) => read_dir,
+        // A tree that was never blessed is simply empty.
+        Err(e) if e.kind() == io::ErrorKind::NotFound && rel.as_str().is_empty() => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in read_dir {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "non-UTF-8 filename in expected tree",
+            )
+        })?;
+        let rel = rel.join(name);
+        if entry.file_type()?.is_dir() {
+            read_tree(root, &rel, files)?;
+        } else {
+            let data = fs::read(root.join(&rel))?;
+            files.insert(rel, data);
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the directory `src` to `dst` recursively, a missing `src` results
+/// in an empty `dst`.
+fn copy_dir(src: &Utf8Path, dst: &Utf8Path) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+
+    let read_dir = match fs::read_dir(src) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in read_dir {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_str().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "non-UTF-8 filename in fixture")
+        })?;
+        if entry.file_type()?.is_dir() {
+            copy_dir(&src.join(name), &dst.join(name))?;
+        } else {
+            fs::copy(src.join(name), dst.join(name))?;
+        }
+    }
+
+    Ok(())
+}
+
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Appends a line diff of `expected` and `actual` to `report`, showing a few
+/// unchanged lines around every change.
+fn diff_lines(report: &mut String, expected: &str, actual: &str) {
+    let a: Vec<_> = expected.lines().collect();
+    let b: Vec<_> = actual.lines().collect();
+
+    let prefix = a.iter().zip(&b).take_while(|(a, b)| a == b).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut lines: Vec<Line> = a[..prefix].iter().map(|line| Line::Same(line)).collect();
+    lines.extend(diff_middle(a_mid, b_mid));
+    lines.extend(a[a.len() - suffix..].iter().map(|line| Line::Same(line)));
+
+    // Only unchanged lines close to a change are printed.
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+    let near = |i: usize| {
+        changed
+            .iter()
+            .any(|&c| c.saturating_sub(CONTEXT) <= i && i <= c + CONTEXT)
+    };
+
+    let mut skipped = false;
+    for (i, line) in lines.iter().enumerate() {
+        match line {
+            Line::Same(_) if !near(i) => {
+                if !skipped {
+                    report.push_str("  ...\n");
+                    skipped = true;
+                }
+                continue;
+            }
+            Line::Same(line) => {
+                let _ = writeln!(report, "  {line}");
+            }
+            Line::Removed(line) => {
+                let _ = writeln!(report, "- {line}");
+            }
+            Line::Added(line) => {
+                let _ = writeln!(report, "+ {line}");
+            }
+        }
+        skipped = false;
+    }
+}
+
+/// Diffs the lines between the common prefix and suffix using the longest
+/// common subsequence, or as a plain replacement if that would be too slow.
+fn diff_middle<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<Line<'a>> {
+    const MAX_CELLS: usize = 1 << 20;
+
+    if a.len().saturating_mul(b.len()) > MAX_CELLS {
+        let removed = a.iter().map(|line| Line::Removed(line));
+        return removed
+            .chain(b.iter().map(|line| Line::Added(line)))
+            .collect();
+    }
+
+    // lcs[i][j] is the length of the LCS of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = match a[i] == b[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(Line::Same(a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(Line::Removed(a[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(b[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::Output;
+    use crate::test_utils::TempDir;
+
+    fn fixture() -> Fixture {
+        let source = TempDir::new("source");
+        fs::create_dir_all(source.join("posts")).unwrap();
+        fs::write(source.join("posts/a.md"), "a").unwrap();
+        fs::write(source.join("posts/b.md"), "b").unwrap();
+        fs::write(source.join("about.md"), "about").unwrap();
+
+        Fixture::new(&*source, (), |root| {
+            let mut config = Blueprint::<()>::new();
+            let posts = config
+                .task()
+                .name("posts")
+                .glob(format!("{root}/posts/*.md"))?
+                .map(|_, _, input| Ok(String::from_utf8(input.read()?.into())?));
+            let about = config
+                .task()
+                .name("about")
+                .glob(format!("{root}/about.md"))?
+                .map(|_, _, input| Ok(String::from_utf8(input.read()?.into())?));
+            config.task().name("index").using(posts).merge(|_, posts| {
+                let body: String = posts.values().cloned().collect();
+                Ok(Output::html("index", body))
+            });
+            config
+                .task()
+                .name("about page")
+                .using(about)
+                .merge(|_, about| {
+                    let body: String = about.values().cloned().collect();
+                    Ok(Output::html("about", body))
+                });
+            Ok(config)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rebuild_reruns_affected_tasks() {
+        let mut fixture = fixture();
+        assert_eq!(fixture.site().text("index.html"), Some("ab"));
+
+        fixture.write("posts/b.md", "c").unwrap();
+        let rebuild = fixture.rebuild().unwrap();
+
+        rebuild.assert_executed(["posts", "index"]);
+        assert_eq!(fixture.site().text("index.html"), Some("ac"));
+        assert_eq!(fixture.site().text("about/index.html"), Some("about"));
+    }
+
+    #[test]
+    fn test_golden_reports_differences() {
+        let fixture = fixture();
+        let expected = fixture.root().join("expected");
+        bless(fixture.site(), &expected).unwrap();
+        assert!(compare(fixture.site(), &expected).unwrap().is_none());
+
+        fs::write(expected.join("index.html"), "ax").unwrap();
+        fs::write(expected.join("gone.html"), "").unwrap();
+        let report = compare(fixture.site(), &expected).unwrap().unwrap();
+
+        assert!(report.contains("~~~ index.html\n- ax\n+ ab\n"), "{report}");
+        assert!(report.contains("--- gone.html (missing file)"), "{report}");
+    }
+
+    #[test]
+    fn test_failed_setup_removes_root() {
+        let source = TempDir::new("source");
+        fs::write(source.join("about.md"), "about").unwrap();
+
+        let mut copy = None;
+        let result = Fixture::new(&*source, (), |root| {
+            copy = Some(root.to_owned());
+            Err(BuildError::Other(anyhow::anyhow!("setup failed")).into())
+        });
+
+        assert!(result.is_err());
+        assert!(!copy.unwrap().exists());
+    }
+
+    #[test]
+    fn test_diff_lines_context() {
+        let expected = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10";
+        let actual = "1\n2\n3\n4\n5\nsix\n7\n8\n9\n10";
+        let mut report = String::new();
+        diff_lines(&mut report, expected, actual);
+
+        assert_eq!(
+            report,
+            "  ...\n  3\n  4\n  5\n- 6\n+ six\n  7\n  8\n  9\n  ...\n"
+        );
+    }
+}
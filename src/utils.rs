@@ -4,6 +4,7 @@ use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use camino::{Utf8Path, Utf8PathBuf};
+use glob::Pattern;
 use indicatif::ProgressStyle;
 use rayon::prelude::*;
 use tracing::{Level, info, span};
@@ -11,6 +12,7 @@ use tracing_indicatif::span_ext::IndicatifSpanExt;
 
 use crate::core::Hash32;
 use crate::error::StepCopyStatic;
+use crate::vfs::Vfs;
 
 /// Configuration for progress bar styles displayed during builds.
 ///
@@ -94,9 +96,9 @@ fn is_unchanged(src: &Path, dst: &Path) -> bool {
 
 #[derive(Clone, Debug)]
 pub(crate) struct StaticFileEntry {
-    pub(crate) src: PathBuf,
     pub(crate) dst: PathBuf,
-    pub(crate) source_utf8: Utf8PathBuf,
+    /// Path of the source file in the [`Vfs`].
+    pub(crate) source: Utf8PathBuf,
     pub(crate) dist_rel: Utf8PathBuf,
 }
 
@@ -104,6 +106,7 @@ pub(crate) struct StaticFileEntry {
 pub(crate) fn collect_static(
     copied: &[(String, String)],
     out_dir: &Utf8Path,
+    vfs: &dyn Vfs,
 ) -> Result<Vec<StaticFileEntry>, StepCopyStatic> {
     if copied.is_empty() {
         return Ok(vec![]);
@@ -143,7 +146,7 @@ pub(crate) fn collect_static(
         let target = out_dir.as_std_path().join(into);
         let dist_rel = Utf8Path::new(into);
 
-        let metadata = fs::metadata(from).map_err(|e| {
+        let stat = vfs.stat(Utf8Path::new(from)).map_err(|e| {
             if e.kind() == std::io::ErrorKind::NotFound {
                 StepCopyStatic::MissingSource(from.clone())
             } else {
@@ -151,15 +154,12 @@ pub(crate) fn collect_static(
             }
         })?;
 
-        if metadata.is_dir() {
-            collect_files(from, &target, dist_rel, &mut files)?;
+        if stat.is_dir {
+            collect_files(vfs, from, &target, dist_rel, &mut files)?;
         } else {
-            let source_utf8 = Utf8PathBuf::try_from(PathBuf::from(from))
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
             files.push(StaticFileEntry {
-                src: PathBuf::from(from),
                 dst: target,
-                source_utf8,
+                source: Utf8PathBuf::from(from),
                 dist_rel: dist_rel.to_path_buf(),
             });
         }
@@ -177,6 +177,7 @@ pub(crate) fn collect_static(
 pub(crate) fn copy_static_entries(
     files: &[StaticFileEntry],
     style: &ProgressStyle,
+    vfs: &dyn Vfs,
 ) -> Result<Vec<(Utf8PathBuf, Utf8PathBuf)>, StepCopyStatic> {
     if files.is_empty() {
         return Ok(vec![]);
@@ -204,11 +205,22 @@ pub(crate) fn copy_static_entries(
     let entries: Vec<(Utf8PathBuf, Utf8PathBuf)> = files
         .par_iter()
         .map(|f| -> std::io::Result<(Utf8PathBuf, Utf8PathBuf)> {
-            if !is_unchanged(&f.src, &f.dst) {
-                fs::copy(&f.src, &f.dst)?;
+            match vfs.local_path(&f.source) {
+                Some(src) => {
+                    if !is_unchanged(src.as_std_path(), &f.dst) {
+                        fs::copy(src, &f.dst)?;
+                    }
+                }
+                // Files which only exist in memory are compared by content.
+                None => {
+                    let data = vfs.read(&f.source)?;
+                    if fs::read(&f.dst).ok().as_deref() != Some(&*data) {
+                        fs::write(&f.dst, data)?;
+                    }
+                }
             }
             span.pb_inc(1);
-            Ok((f.source_utf8.clone(), f.dist_rel.clone()))
+            Ok((f.source.clone(), f.dist_rel.clone()))
         })
         .collect::<std::io::Result<_>>()?;
 
@@ -217,39 +229,30 @@ pub(crate) fn copy_static_entries(
     Ok(entries)
 }
 
-/// Recursively walks `src`, appending one [`StaticFileEntry`] per file to `files`.
-/// Directory creation is deferred to the caller.
+/// Finds every file under the directory `src`, appending one
+/// [`StaticFileEntry`] per file to `files`. Directory creation is deferred to
+/// the caller.
 fn collect_files(
-    src: impl AsRef<Path>,
-    dst: impl AsRef<Path>,
+    vfs: &dyn Vfs,
+    src: &str,
+    dst: &Path,
     dist_rel: &Utf8Path,
     files: &mut Vec<StaticFileEntry>,
 ) -> std::io::Result<()> {
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let name = entry.file_name();
-        let name_str = name.to_str().ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::InvalidData, "non-UTF-8 filename")
-        })?;
-
-        if entry.file_type()?.is_dir() {
-            collect_files(
-                entry.path(),
-                dst.as_ref().join(&name),
-                &dist_rel.join(name_str),
-                files,
-            )?;
-        } else {
-            let src_path = entry.path();
-            let source_utf8 = Utf8PathBuf::try_from(src_path.clone())
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-            files.push(StaticFileEntry {
-                dst: dst.as_ref().join(&name),
-                src: src_path,
-                source_utf8,
-                dist_rel: dist_rel.join(name_str),
-            });
-        }
+    let src = src.trim_end_matches('/');
+    let prefix = crate::vfs::normalize(Utf8Path::new(src));
+
+    for source in vfs.glob(&format!("{}/**/*", Pattern::escape(src)))? {
+        let path = crate::vfs::normalize(&source);
+        let rel = Utf8Path::new(&path)
+            .strip_prefix(&prefix)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        files.push(StaticFileEntry {
+            dst: dst.join(rel),
+            dist_rel: dist_rel.join(rel),
+            source,
+        });
     }
 
     Ok(())
@@ -258,12 +261,14 @@ fn collect_files(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vfs::Disk;
 
     #[test]
     fn clone_static_errors_for_missing_source() {
         let result = collect_static(
             &[("assets".to_string(), "missing-static-source".to_string())],
             Utf8Path::new("dist"),
+            &Disk,
         );
 
         assert!(matches!(
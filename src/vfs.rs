@@ -0,0 +1,214 @@
+//! The filesystem that loaders read their input files from.
+//!
+//! Every loader provided by this crate finds its files with [`Vfs::glob`] and
+//! reads them with [`Vfs::read`], the same goes for [`Input::read`] and for
+//! files added with [`Blueprint::copy_static`]. By default this is the real
+//! disk, but a [`Blueprint`] can be configured with any other [`Vfs`] using
+//! [`Blueprint::set_vfs`]:
+//!
+//! - [`Disk`], the current working directory, used by default.
+//! - [`MemoryFs`], files kept in memory, useful in tests.
+//! - [`EmbeddedFs`], files embedded in the executable with
+//!   [`embed_dir!`](crate::embed_dir), for single-binary site generators.
+//! - `ArchiveFs`, the contents of a zip or tar archive, behind the `archive`
+//!   feature.
+//!
+//! Loaders which delegate to external tools, like esbuild, Svelte or the
+//! Sass compiler, still read their entry points and imports from the disk.
+//!
+//! [`Input::read`]: crate::loader::Input::read
+//! [`Blueprint`]: crate::Blueprint
+//! [`Blueprint::copy_static`]: crate::Blueprint::copy_static
+//! [`Blueprint::set_vfs`]: crate::Blueprint::set_vfs
+
+mod memory;
+pub use memory::{EmbeddedFs, MemoryFs};
+
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "archive")]
+pub use archive::ArchiveFs;
+
+use std::fs;
+use std::io;
+use std::time::SystemTime;
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use glob::{MatchOptions, Pattern};
+
+use crate::core::Hash32;
+
+/// Metadata of a file or directory, returned by [`Vfs::stat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+    /// Size of the file in bytes, zero for directories.
+    pub len: u64,
+    /// Time of the last modification, if the filesystem keeps track of it.
+    pub modified: Option<SystemTime>,
+    /// Whether the path is a directory.
+    pub is_dir: bool,
+}
+
+/// A read-only filesystem the input files of a website are loaded from.
+///
+/// Paths use `/` as the separator and are relative to the root of the
+/// filesystem, the same way they are written in globs passed to loaders.
+pub trait Vfs: Send + Sync {
+    /// Returns the files matching the glob `pattern`, sorted by path.
+    /// Directories are never returned.
+    fn glob(&self, pattern: &str) -> io::Result<Vec<Utf8PathBuf>>;
+
+    /// Reads the whole content of a file.
+    fn read(&self, path: &Utf8Path) -> io::Result<Box<[u8]>>;
+
+    /// Returns the metadata of a file or directory.
+    fn stat(&self, path: &Utf8Path) -> io::Result<FileStat>;
+
+    /// Returns the BLAKE3 hash of the content of a file.
+    fn hash(&self, path: &Utf8Path) -> io::Result<[u8; 32]> {
+        Ok(Hash32::hash(self.read(path)?).to_bytes())
+    }
+
+    /// Returns the path of the file on the local disk, if it has one.
+    ///
+    /// Such files are copied to `dist` without being read into memory, and
+    /// their hashes are cached between runs.
+    fn local_path(&self, _: &Utf8Path) -> Option<Utf8PathBuf> {
+        None
+    }
+}
+
+/// The real filesystem, paths are relative to the current working directory.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Disk;
+
+impl Vfs for Disk {
+    fn glob(&self, pattern: &str) -> io::Result<Vec<Utf8PathBuf>> {
+        let paths =
+            glob::glob(pattern).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let mut files = Vec::new();
+        for path in paths {
+            let path = path.map_err(glob::GlobError::into_error)?;
+            if path.is_dir() {
+                continue;
+            }
+            let path = Utf8PathBuf::try_from(path)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            files.push(path);
+        }
+
+        Ok(files)
+    }
+
+    fn read(&self, path: &Utf8Path) -> io::Result<Box<[u8]>> {
+        fs::read(path).map(Into::into)
+    }
+
+    fn stat(&self, path: &Utf8Path) -> io::Result<FileStat> {
+        let meta = fs::metadata(path)?;
+        Ok(FileStat {
+            len: if meta.is_dir() { 0 } else { meta.len() },
+            modified: meta.modified().ok(),
+            is_dir: meta.is_dir(),
+        })
+    }
+
+    fn hash(&self, path: &Utf8Path) -> io::Result<[u8; 32]> {
+        Hash32::hash_file(path).map(Hash32::to_bytes)
+    }
+
+    fn local_path(&self, path: &Utf8Path) -> Option<Utf8PathBuf> {
+        Some(path.to_owned())
+    }
+}
+
+/// Normalizes a path for comparison with the keys of the filesystems kept
+/// in memory, `.` components and leading or repeated separators are dropped.
+pub(crate) fn normalize(path: &Utf8Path) -> String {
+    let mut normal = String::with_capacity(path.as_str().len());
+    for component in path.components() {
+        if let Utf8Component::Normal(name) = component {
+            if !normal.is_empty() {
+                normal.push('/');
+            }
+            normal.push_str(name);
+        }
+    }
+    normal
+}
+
+/// Returns the paths matching `pattern`, with the same semantics as the glob
+/// walk used by [`Disk`], where `*` never matches a separator.
+fn glob_paths<'a>(
+    pattern: &str,
+    paths: impl Iterator<Item = &'a str>,
+) -> io::Result<Vec<Utf8PathBuf>> {
+    let pattern = Pattern::new(&normalize(Utf8Path::new(pattern)))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+
+    let mut files: Vec<_> = paths
+        .filter(|path| pattern.matches_with(path, options))
+        .map(Utf8PathBuf::from)
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Returns the metadata of `path` in a filesystem that only stores files,
+/// where directories exist implicitly as prefixes of the stored paths.
+fn stat_paths<'a>(
+    path: &Utf8Path,
+    mut files: impl Iterator<Item = (&'a str, u64)>,
+) -> io::Result<FileStat> {
+    let path = normalize(path);
+    let is_dir = |name: &str| {
+        path.is_empty()
+            || name
+                .strip_prefix(path.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+    };
+
+    files
+        .find_map(|(name, len)| match name == path {
+            true => Some(FileStat {
+                len,
+                modified: None,
+                is_dir: false,
+            }),
+            false => is_dir(name).then_some(FileStat {
+                len: 0,
+                modified: None,
+                is_dir: true,
+            }),
+        })
+        .ok_or_else(|| not_found(&path))
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("no such file: {path}"))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_glob_skips_dirs() {
+        let files = Disk.glob("src/vfs*").unwrap();
+        assert_eq!(files, [Utf8PathBuf::from("src/vfs.rs")]);
+        assert!(Disk.stat(Utf8Path::new("src/vfs")).unwrap().is_dir);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Utf8Path::new("./content//a.md")), "content/a.md");
+        assert_eq!(normalize(Utf8Path::new("/content/")), "content");
+    }
+}
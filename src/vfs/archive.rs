@@ -0,0 +1,249 @@
+use std::io::{self, Cursor, Read};
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use flate2::read::GzDecoder;
+
+use super::{FileStat, MemoryFs, Vfs};
+
+/// Upper bound on the decompressed size of all files in an archive, sizes
+/// recorded in archive headers are not trusted.
+const MAX_SIZE: u64 = 1 << 30;
+
+/// A filesystem read from a zip or tar archive.
+///
+/// The whole archive is decompressed into memory when it's opened, up to
+/// 1 GiB of file contents. Only regular files are loaded, links are skipped.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use hauchiwa::Blueprint;
+/// use hauchiwa::vfs::ArchiveFs;
+///
+/// let config = Blueprint::<()>::new().set_vfs(ArchiveFs::open("content.tar.gz")?);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ArchiveFs {
+    files: MemoryFs,
+}
+
+impl ArchiveFs {
+    /// Opens an archive file, the format is chosen by its extension, one of
+    /// `.zip`, `.tar`, `.tar.gz` or `.tgz`.
+    pub fn open(path: impl AsRef<Utf8Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+
+        let name = path.file_name().unwrap_or_default();
+        if name.ends_with(".zip") {
+            Self::from_zip(&data)
+        } else if name.ends_with(".tar") {
+            Self::from_tar(&data)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Self::from_tar_gz(&data)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown archive format: {path}"),
+            ))
+        }
+    }
+
+    /// Reads a zip archive.
+    pub fn from_zip(data: &[u8]) -> io::Result<Self> {
+        read_zip(data, MAX_SIZE).map(|files| Self { files })
+    }
+
+    /// Reads an uncompressed tar archive.
+    pub fn from_tar(data: &[u8]) -> io::Result<Self> {
+        read_tar(data, MAX_SIZE).map(|files| Self { files })
+    }
+
+    /// Reads a gzip compressed tar archive.
+    pub fn from_tar_gz(data: &[u8]) -> io::Result<Self> {
+        read_tar(GzDecoder::new(data), MAX_SIZE).map(|files| Self { files })
+    }
+}
+
+impl Vfs for ArchiveFs {
+    fn glob(&self, pattern: &str) -> io::Result<Vec<Utf8PathBuf>> {
+        self.files.glob(pattern)
+    }
+
+    fn read(&self, path: &Utf8Path) -> io::Result<Box<[u8]>> {
+        self.files.read(path)
+    }
+
+    fn stat(&self, path: &Utf8Path) -> io::Result<FileStat> {
+        self.files.stat(path)
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Rejects paths which would escape the root of the archive.
+fn entry_path(name: &str) -> io::Result<&str> {
+    let safe = Utf8Path::new(name)
+        .components()
+        .all(|c| matches!(c, Utf8Component::Normal(_) | Utf8Component::CurDir));
+    match safe {
+        true => Ok(name),
+        false => Err(invalid(format!("unsafe path in archive: {name}"))),
+    }
+}
+
+/// Reads a single file, taking its size from what's left of `budget`.
+fn read_entry(reader: impl Read, budget: &mut u64) -> io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    reader.take(*budget + 1).read_to_end(&mut contents)?;
+    *budget = budget
+        .checked_sub(contents.len() as u64)
+        .ok_or_else(|| invalid(format!("archive is larger than {MAX_SIZE} bytes")))?;
+    Ok(contents)
+}
+
+fn read_tar(reader: impl Read, mut budget: u64) -> io::Result<MemoryFs> {
+    let mut files = MemoryFs::new();
+    for entry in tar::Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        // directories, links and pax headers
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?;
+        let name = path
+            .to_str()
+            .ok_or_else(|| invalid("non-UTF-8 path in tar archive"))?
+            .to_owned();
+        let contents = read_entry(&mut entry, &mut budget)?;
+        files.insert(entry_path(&name)?, contents);
+    }
+    Ok(files)
+}
+
+fn read_zip(data: &[u8], mut budget: u64) -> io::Result<MemoryFs> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+    let mut files = MemoryFs::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if !file.is_file() {
+            continue;
+        }
+
+        let name = entry_path(file.name())?.to_owned();
+        let contents = read_entry(&mut file, &mut budget)?;
+        files.insert(name, contents);
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use zip::CompressionMethod;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    const TAR_BLOCK: usize = 512;
+
+    /// Writes the header by hand, `tar::Builder` refuses unsafe paths.
+    fn tar_entry(tar: &mut Vec<u8>, name: &str, kind: u8, body: &[u8]) {
+        let mut header = [0u8; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", body.len()).as_bytes());
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+
+        header[148..156].fill(b' ');
+        let sum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+        header[148..155].copy_from_slice(format!("{sum:06o}\0").as_bytes());
+
+        tar.extend_from_slice(&header);
+        tar.extend_from_slice(body);
+        tar.resize(tar.len().div_ceil(TAR_BLOCK) * TAR_BLOCK, 0);
+    }
+
+    fn tar() -> Vec<u8> {
+        let long = format!("content/{}.md", "x".repeat(120));
+        let pax = format!("{} path={long}\n", long.len() + 10);
+
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, "content/", b'5', b"");
+        tar_entry(&mut tar, "./content/a.md", b'0', b"hello");
+        tar_entry(&mut tar, "PaxHeader", b'x', pax.as_bytes());
+        tar_entry(&mut tar, "ignored", b'0', b"long");
+        tar.resize(tar.len() + 2 * TAR_BLOCK, 0);
+        tar
+    }
+
+    #[test]
+    fn test_tar() {
+        let fs = ArchiveFs::from_tar(&tar()).unwrap();
+        assert_eq!(&*fs.read("content/a.md".into()).unwrap(), b"hello");
+        assert_eq!(fs.glob("content/*.md").unwrap().len(), 2);
+        assert!(fs.stat("content".into()).unwrap().is_dir);
+
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&tar()).unwrap();
+        let fs = ArchiveFs::from_tar_gz(&gz.finish().unwrap()).unwrap();
+        assert_eq!(fs.glob("content/*.md").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_tar_rejects_unsafe_paths() {
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, "../escape", b'0', b"");
+        tar.resize(tar.len() + 2 * TAR_BLOCK, 0);
+        assert!(ArchiveFs::from_tar(&tar).is_err());
+    }
+
+    #[test]
+    fn test_tar_rejects_oversized_contents() {
+        assert!(read_tar(&tar()[..], 8).is_err());
+        assert!(read_tar(&tar()[..], 9).is_ok());
+    }
+
+    fn zip() -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        zip.add_directory("content/", stored).unwrap();
+        zip.start_file("content/a.md", stored).unwrap();
+        zip.write_all(b"stored").unwrap();
+        zip.start_file("content/b.md", deflated).unwrap();
+        zip.write_all(b"deflated deflated deflated").unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_zip() {
+        let fs = ArchiveFs::from_zip(&zip()).unwrap();
+        assert_eq!(
+            fs.glob("content/*").unwrap(),
+            ["content/a.md", "content/b.md"]
+        );
+        assert_eq!(&*fs.read("content/a.md".into()).unwrap(), b"stored");
+        assert_eq!(
+            &*fs.read("content/b.md".into()).unwrap(),
+            b"deflated deflated deflated"
+        );
+    }
+
+    #[test]
+    fn test_zip_rejects_oversized_contents() {
+        assert!(read_zip(&zip(), 31).is_err());
+        assert!(read_zip(&zip(), 32).is_ok());
+    }
+}
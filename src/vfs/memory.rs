@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Arc;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use super::{FileStat, Vfs, glob_paths, normalize, not_found, stat_paths};
+
+/// A filesystem kept in memory.
+///
+/// # Example
+///
+/// ```rust
+/// use hauchiwa::Blueprint;
+/// use hauchiwa::vfs::MemoryFs;
+///
+/// let files = MemoryFs::new()
+///     .with("content/index.md", "# Hello")
+///     .with("static/style.css", "body {}");
+///
+/// let config = Blueprint::<()>::new()
+///     .set_vfs(files)
+///     .copy_static("static", "static");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs {
+    files: BTreeMap<String, Arc<[u8]>>,
+}
+
+impl MemoryFs {
+    /// Creates an empty filesystem.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing any previous file with the same path.
+    #[must_use]
+    pub fn with(mut self, path: impl AsRef<Utf8Path>, contents: impl AsRef<[u8]>) -> Self {
+        self.insert(path, contents);
+        self
+    }
+
+    /// Adds a file, replacing any previous file with the same path.
+    pub fn insert(&mut self, path: impl AsRef<Utf8Path>, contents: impl AsRef<[u8]>) {
+        let path = normalize(path.as_ref());
+        self.files.insert(path, contents.as_ref().into());
+    }
+
+    /// Removes a file, returns `false` if there was no such file.
+    pub fn remove(&mut self, path: impl AsRef<Utf8Path>) -> bool {
+        self.files.remove(&normalize(path.as_ref())).is_some()
+    }
+
+    /// Returns the number of files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns `true` if there are no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl Vfs for MemoryFs {
+    fn glob(&self, pattern: &str) -> io::Result<Vec<Utf8PathBuf>> {
+        glob_paths(pattern, self.files.keys().map(String::as_str))
+    }
+
+    fn read(&self, path: &Utf8Path) -> io::Result<Box<[u8]>> {
+        let path = normalize(path);
+        match self.files.get(&path) {
+            Some(data) => Ok(data.as_ref().into()),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn stat(&self, path: &Utf8Path) -> io::Result<FileStat> {
+        let files = self.files.iter();
+        stat_paths(
+            path,
+            files.map(|(path, data)| (path.as_str(), data.len() as u64)),
+        )
+    }
+}
+
+/// A filesystem embedded in the executable, created with
+/// [`embed_dir!`](crate::embed_dir).
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedFs {
+    files: &'static [(&'static str, &'static [u8])],
+}
+
+impl EmbeddedFs {
+    /// Creates a filesystem from a list of paths and their contents.
+    pub const fn new(files: &'static [(&'static str, &'static [u8])]) -> Self {
+        Self { files }
+    }
+
+    fn get(&self, path: &str) -> Option<&'static [u8]> {
+        self.files
+            .iter()
+            .find(|(name, _)| *name == path)
+            .map(|(_, data)| *data)
+    }
+}
+
+impl Vfs for EmbeddedFs {
+    fn glob(&self, pattern: &str) -> io::Result<Vec<Utf8PathBuf>> {
+        glob_paths(pattern, self.files.iter().map(|(path, _)| *path))
+    }
+
+    fn read(&self, path: &Utf8Path) -> io::Result<Box<[u8]>> {
+        let path = normalize(path);
+        match self.get(&path) {
+            Some(data) => Ok(data.into()),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn stat(&self, path: &Utf8Path) -> io::Result<FileStat> {
+        let files = self.files.iter();
+        stat_paths(path, files.map(|(path, data)| (*path, data.len() as u64)))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn files() -> MemoryFs {
+        MemoryFs::new()
+            .with("content/a.md", "a")
+            .with("./content/posts/b.md", "b")
+            .with("content/posts/c.txt", "c")
+    }
+
+    #[test]
+    fn test_glob() {
+        let fs = files();
+        assert_eq!(fs.glob("content/*.md").unwrap(), ["content/a.md"]);
+        assert_eq!(
+            fs.glob("content/**/*.md").unwrap(),
+            ["content/a.md", "content/posts/b.md"]
+        );
+        assert_eq!(fs.glob("./content/posts/*").unwrap().len(), 2);
+        assert!(fs.glob("content/[").is_err());
+    }
+
+    #[test]
+    fn test_read_and_stat() {
+        let fs = files();
+        assert_eq!(&*fs.read("content/posts/b.md".into()).unwrap(), b"b");
+        assert_eq!(
+            fs.hash("content/a.md".into()).unwrap(),
+            *blake3::hash(b"a").as_bytes()
+        );
+
+        assert!(fs.stat("content/posts".into()).unwrap().is_dir);
+        assert!(!fs.stat("content/a.md".into()).unwrap().is_dir);
+        assert!(fs.stat("content/post".into()).is_err());
+
+        let err = fs.read("content/missing.md".into()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_embedded() {
+        static FILES: EmbeddedFs =
+            EmbeddedFs::new(&[("content/a.md", b"a"), ("static/x.css", b"")]);
+        assert_eq!(FILES.glob("*/*.md").unwrap(), ["content/a.md"]);
+        assert_eq!(&*FILES.read("./content/a.md".into()).unwrap(), b"a");
+        assert!(FILES.stat("static".into()).unwrap().is_dir);
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn test_embed_dir() {
+        static FILES: EmbeddedFs = crate::embed_dir!("./src/vfs/");
+        assert_eq!(
+            FILES.glob("src/vfs/*.rs").unwrap(),
+            ["src/vfs/archive.rs", "src/vfs/memory.rs"]
+        );
+        let source = FILES.read("src/vfs/memory.rs".into()).unwrap();
+        assert_eq!(&*source, include_bytes!("memory.rs"));
+    }
+}